@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
 use std::io::{Read, Write, Seek};
+use std::ops::Range;
+use std::str::FromStr;
 
 #[macro_export]
 macro_rules! wadup_start {
@@ -18,6 +21,91 @@ macro_rules! wadup_start {
     }
 }
 
+/// Exports `wadup_reduce`, which the host runs once after every input and carve has been
+/// processed. Use [`WadupRows`] to read back the rows emitted during the run.
+#[macro_export]
+macro_rules! wadup_reduce {
+    ($name:ident) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn wadup_reduce() {
+            if let Err(err) = $name() {
+                let err = err.to_string();
+                let err = err.as_bytes();
+                unsafe {
+                    $crate::wadup_error(err.as_ptr(), err.len());
+                }
+            }
+        }
+    }
+}
+
+/// Embeds signatures in the module so the host can skip inputs that cannot match without
+/// instantiating it. Each signature is `"offset:hexbytes"` or `"offset:hexbytes/hexmask"`; the
+/// module runs if any of them matches.
+///
+/// ```ignore
+/// wadup_signatures!("0:4444");
+/// ```
+#[macro_export]
+macro_rules! wadup_signatures {
+    ($($signature:literal),+ $(,)?) => {
+        const _: () = {
+            const SIGNATURES: &str = concat!($($signature, "\n"),+);
+
+            #[used]
+            #[unsafe(link_section = "wadup_signatures")]
+            static WADUP_SIGNATURES: [u8; SIGNATURES.len()] = $crate::signature_section(SIGNATURES);
+        };
+    }
+}
+
+#[doc(hidden)]
+pub const fn signature_section<const N: usize>(signatures: &str) -> [u8; N] {
+    let bytes = signatures.as_bytes();
+    let mut section = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        section[i] = bytes[i];
+        i += 1;
+    }
+    section
+}
+
+/// Parameters passed to this module by the host, from the module's manifest, the `module_args`
+/// section of the config file and `--module-arg` flags.
+#[derive(Debug, Clone, Default)]
+pub struct WadupConfig {
+    values: BTreeMap<String, String>,
+}
+
+impl WadupConfig {
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Parses a parameter, returning `Ok(None)` if it was not given.
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, T::Err> {
+        self.get_str(key).map(str::parse).transpose()
+    }
+
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, T::Err> {
+        Ok(self.get(key)?.unwrap_or(default))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+pub fn config() -> WadupConfig {
+    let encoded = read_host_buffer(unsafe { wadup_config_len() }, |buffer, offset, length| unsafe {
+        wadup_config_read(buffer, offset, length)
+    });
+    WadupConfig {
+        values: decode_pairs(&encoded).into_iter().collect(),
+    }
+}
+
 pub struct WadupOutput {
     fd: i32,
     pos: u64,
@@ -35,6 +123,62 @@ impl WadupOutput {
     fn len(&self) -> u64 {
         unsafe { wadup_output_len(self.fd) }
     }
+
+    /// Hashes a range of this buffer on the host.
+    pub fn hash(&self, algorithm: WadupHash, range: Range<u64>) -> Vec<u8> {
+        hash(algorithm, self.fd, range)
+    }
+
+    /// Shannon entropy in bits per byte of each `block_size` block of a range of this buffer, or
+    /// of the whole range when `block_size` is 0.
+    pub fn entropy(&self, range: Range<u64>, block_size: u64) -> Vec<f64> {
+        entropy(self.fd, range, block_size)
+    }
+
+    /// Counts of each byte value in each `block_size` block of a range of this buffer.
+    pub fn histogram(&self, range: Range<u64>, block_size: u64) -> Vec<[u64; 256]> {
+        histogram(self.fd, range, block_size)
+    }
+
+    /// Decompresses a range of this buffer on the host into a new output buffer. Returns `None` if
+    /// the data is corrupt or larger than the limit.
+    pub fn decompress(&self, compression: WadupCompression, range: Range<u64>, options: &DecompressOptions) -> Option<WadupOutput> {
+        decompress(compression, self.fd, range, options)
+    }
+
+    /// Finds every occurrence of the patterns, including overlapping ones, in a range of this buffer.
+    pub fn search(&self, patterns: &[&[u8]], range: Range<u64>) -> WadupSearch {
+        WadupSearch::patterns(patterns, self.fd, range)
+    }
+
+    /// Finds successive non-overlapping matches of a regex in a range of this buffer.
+    pub fn search_regex(&self, pattern: &str, range: Range<u64>) -> WadupSearch {
+        WadupSearch::regex(pattern, self.fd, range)
+    }
+
+    /// Runs another module on a range of this buffer and waits for it to finish.
+    pub fn invoke(&self, module: &str, offset: u64, length: u64) -> WadupInvocation {
+        WadupInvocation::run(module, self.fd, offset, length)
+    }
+
+    /// Submits the current contents as a new blob derived from the input, routed like a carve.
+    pub fn submit(&self) {
+        self.submit_to(&CarveTarget::default());
+    }
+
+    pub fn submit_to(&self, target: &CarveTarget) {
+        let modules = target.modules.join("\n");
+        let hint = target.hint.unwrap_or_default();
+        let file_name = target.file_name.unwrap_or_default();
+        unsafe {
+            wadup_output_submit(
+                self.fd,
+                modules.as_ptr(), modules.len(),
+                hint.as_ptr(), hint.len(),
+                file_name.as_ptr(), file_name.len(),
+            )
+        }
+    }
 }
 
 
@@ -80,6 +224,278 @@ impl Seek for WadupOutput {
     }
 }
 
+/// Hash algorithms computed natively by the host. The similarity digests (ssdeep and TLSH) are
+/// returned as their usual text form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WadupHash {
+    Md5 = 1,
+    Sha1 = 2,
+    Sha256 = 3,
+    Ssdeep = 4,
+    Tlsh = 5,
+}
+
+fn hash(algorithm: WadupHash, fd: i32, range: Range<u64>) -> Vec<u8> {
+    let mut digest = vec![0u8; 32];
+    let length = range.end.saturating_sub(range.start);
+    let len = unsafe { wadup_hash(algorithm as u32, fd, range.start, length, digest.as_mut_ptr(), digest.len()) };
+    if len > digest.len() {
+        digest.resize(len, 0);
+        unsafe { wadup_hash(algorithm as u32, fd, range.start, length, digest.as_mut_ptr(), digest.len()) };
+    }
+    digest.truncate(len);
+    digest
+}
+
+fn entropy(fd: i32, range: Range<u64>, block_size: u64) -> Vec<f64> {
+    let length = range.end.saturating_sub(range.start);
+    let count = unsafe { wadup_entropy(fd, range.start, length, block_size, std::ptr::null_mut(), 0) };
+    let mut buffer = vec![0u8; (count as usize).checked_mul(8).expect("entropy blocks exceed memory")];
+    unsafe { wadup_entropy(fd, range.start, length, block_size, buffer.as_mut_ptr(), buffer.len()) };
+    buffer.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect()
+}
+
+fn histogram(fd: i32, range: Range<u64>, block_size: u64) -> Vec<[u64; 256]> {
+    let length = range.end.saturating_sub(range.start);
+    let count = unsafe { wadup_histogram(fd, range.start, length, block_size, std::ptr::null_mut(), 0) };
+    let mut buffer = vec![0u8; (count as usize).checked_mul(256 * 8).expect("histogram blocks exceed memory")];
+    unsafe { wadup_histogram(fd, range.start, length, block_size, buffer.as_mut_ptr(), buffer.len()) };
+    buffer.chunks_exact(256 * 8)
+        .map(|block| {
+            let mut histogram = [0u64; 256];
+            for (count, bytes) in histogram.iter_mut().zip(block.chunks_exact(8)) {
+                *count = u64::from_le_bytes(bytes.try_into().unwrap());
+            }
+            histogram
+        })
+        .collect()
+}
+
+/// Compression formats decoded natively by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WadupCompression {
+    Zlib = 1,
+    Gzip = 2,
+    Bzip2 = 3,
+    Xz = 4,
+    Zstd = 5,
+}
+
+/// How much to decompress and whether the host should also route the result as a new blob.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecompressOptions {
+    /// Largest output accepted, capped by the host's configured limit; 0 uses that limit
+    pub limit: u64,
+    /// Route the decompressed data as a blob derived from the input
+    pub submit: bool,
+}
+
+fn decompress(compression: WadupCompression, fd: i32, range: Range<u64>, options: &DecompressOptions) -> Option<WadupOutput> {
+    let length = range.end.saturating_sub(range.start);
+    let fd = unsafe { wadup_decompress(compression as u32, fd, range.start, length, options.limit, options.submit as u32) };
+    (fd >= 0).then_some(WadupOutput { fd, pos: 0 })
+}
+
+/// A match found by the host. Offsets are relative to the start of the searched buffer, not the
+/// searched range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WadupMatch {
+    /// Index of the matching pattern; always 0 for a regex
+    pub pattern: u32,
+    pub start: u64,
+    pub end: u64,
+}
+
+const MATCH_SIZE: usize = 20;
+const MATCH_BATCH: usize = 64;
+
+/// Matches of a host-side search, fetched from the host a batch at a time.
+pub struct WadupSearch {
+    handle: i32,
+    batch: Vec<WadupMatch>,
+    done: bool,
+}
+
+impl WadupSearch {
+    fn patterns(patterns: &[&[u8]], fd: i32, range: Range<u64>) -> WadupSearch {
+        let mut encoded = Vec::new();
+        for pattern in patterns {
+            encoded.extend_from_slice(&(pattern.len() as u32).to_le_bytes());
+            encoded.extend_from_slice(pattern);
+        }
+        let length = range.end.saturating_sub(range.start);
+        let handle = unsafe { wadup_search_patterns(encoded.as_ptr(), encoded.len(), fd, range.start, length) };
+        WadupSearch { handle, batch: Vec::new(), done: false }
+    }
+
+    fn regex(pattern: &str, fd: i32, range: Range<u64>) -> WadupSearch {
+        let length = range.end.saturating_sub(range.start);
+        let handle = unsafe { wadup_search_regex(pattern.as_ptr(), pattern.len(), fd, range.start, length) };
+        WadupSearch { handle, batch: Vec::new(), done: false }
+    }
+}
+
+impl Iterator for WadupSearch {
+    type Item = WadupMatch;
+
+    fn next(&mut self) -> Option<WadupMatch> {
+        if self.batch.is_empty() && !self.done {
+            let mut buffer = [0u8; MATCH_SIZE * MATCH_BATCH];
+            let count = unsafe { wadup_search_next(self.handle, buffer.as_mut_ptr(), MATCH_BATCH as u32) } as usize;
+            self.done = count < MATCH_BATCH;
+            // Reversed so matches can be popped off in order
+            self.batch = buffer[..count * MATCH_SIZE].chunks_exact(MATCH_SIZE).rev()
+                .map(|m| WadupMatch {
+                    pattern: u32::from_le_bytes(m[0..4].try_into().unwrap()),
+                    start: u64::from_le_bytes(m[4..12].try_into().unwrap()),
+                    end: u64::from_le_bytes(m[12..20].try_into().unwrap()),
+                })
+                .collect();
+        }
+        self.batch.pop()
+    }
+}
+
+/// A YARA rule from the host's rules directory that matched the current input.
+#[derive(Debug, Clone, Default)]
+pub struct WadupYaraMatch {
+    pub rule: String,
+    /// The rules file the rule was loaded from, without its extension
+    pub namespace: String,
+    pub tags: Vec<String>,
+    pub strings: Vec<WadupYaraString>,
+}
+
+/// Where one of a matched rule's strings was found in the input.
+#[derive(Debug, Clone)]
+pub struct WadupYaraString {
+    pub identifier: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl WadupYaraMatch {
+    fn decode(encoded: &[u8]) -> Vec<WadupYaraMatch> {
+        let mut matches = Vec::<WadupYaraMatch>::new();
+        for (key, value) in decode_pairs(encoded) {
+            if key == "rule" {
+                matches.push(WadupYaraMatch { rule: value, ..Default::default() });
+                continue;
+            }
+            let Some(current) = matches.last_mut() else {
+                continue;
+            };
+            match key.as_str() {
+                "namespace" => current.namespace = value,
+                "tag" => current.tags.push(value),
+                "string" => {
+                    // The identifier itself never contains a colon
+                    let mut parts = value.splitn(3, ':');
+                    if let (Some(identifier), Some(offset), Some(length)) = (parts.next(), parts.next(), parts.next()) {
+                        current.strings.push(WadupYaraString {
+                            identifier: identifier.to_owned(),
+                            offset: offset.parse().unwrap_or_default(),
+                            length: length.parse().unwrap_or_default(),
+                        });
+                    }
+                },
+                _ => {},
+            }
+        }
+        matches
+    }
+}
+
+/// The result of running another module synchronously. The fuel it used is charged to this module.
+#[derive(Default)]
+pub struct WadupInvocation {
+    /// Output buffers the module created, now owned by this module
+    pub outputs: Vec<WadupOutput>,
+    /// Tags the module's blob carried when it finished, including those inherited from this blob
+    pub tags: BTreeMap<String, String>,
+    pub error: Option<String>,
+}
+
+impl WadupInvocation {
+    fn run(module: &str, fd: i32, offset: u64, length: u64) -> WadupInvocation {
+        let handle = unsafe { wadup_invoke(module.as_ptr(), module.len(), fd, offset, length) };
+        let encoded = read_host_buffer(unsafe { wadup_invocation_len(handle) }, |buffer, offset, length| unsafe {
+            wadup_invocation_read(handle, buffer, offset, length)
+        });
+        let mut invocation = WadupInvocation::default();
+        for (key, value) in decode_pairs(&encoded) {
+            if key == "output" {
+                if let Ok(fd) = value.parse() {
+                    invocation.outputs.push(WadupOutput { fd, pos: 0 });
+                }
+            } else if key == "error" {
+                invocation.error = Some(value);
+            } else if let Some(tag) = key.strip_prefix("tag.") {
+                invocation.tags.insert(tag.to_owned(), value);
+            }
+        }
+        invocation
+    }
+}
+
+/// Where a carve or submitted output should go and what it contains. With no modules the carve is routed as usual,
+/// so routes can select it by its hint or file name.
+#[derive(Default)]
+pub struct CarveTarget<'a> {
+    pub modules: &'a [&'a str],
+    pub hint: Option<&'a str>,
+    pub file_name: Option<&'a str>,
+}
+
+/// A decoding the host applies to a carved range as it is read.
+#[derive(Debug, Clone, Copy)]
+pub enum WadupTransform<'a> {
+    /// XOR with a key repeated from the start of the range
+    Xor(&'a [u8]),
+    /// Standard or URL-safe base64, optionally padded, without whitespace
+    Base64,
+    /// Pairs of hex digits, without separators
+    Hex,
+    /// Reverses the bytes of each 2, 4 or 8 byte word
+    ByteSwap(u8),
+}
+
+impl WadupTransform<'_> {
+    fn spec(&self) -> String {
+        match self {
+            WadupTransform::Xor(key) => format!("xor:{}", key.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+            WadupTransform::Base64 => "base64".to_owned(),
+            WadupTransform::Hex => "hex".to_owned(),
+            WadupTransform::ByteSwap(width) => format!("byteswap:{}", width),
+        }
+    }
+}
+
+/// Where the current input came from.
+#[derive(Debug, Clone, Default)]
+pub struct WadupInputInfo {
+    /// Path of the root input file the blob was derived from
+    pub file_path: String,
+    /// Offset of the blob within the blob it was carved from
+    pub offset: u64,
+    /// Zero for root inputs, one more than the parent for carves
+    pub depth: u32,
+    /// How the blob was derived from its parent, e.g. `"carved from"` or `"decompressed from"`
+    pub lineage: String,
+    /// The format the blob was decompressed from, if any
+    pub compression: Option<String>,
+    /// The (offset, length) fragments of the parent a concatenated blob was stitched from
+    pub ranges: Vec<(u64, u64)>,
+    /// The decoding a transformed blob is read through, e.g. `"xor:5a"` or `"base64"`
+    pub transform: Option<String>,
+    /// The file type the host identified from the blob's magic number, e.g. `"pe"` or `"text"`
+    pub file_type: Option<String>,
+    pub mime: Option<String>,
+    pub parent_module: Option<String>,
+    pub hint: Option<String>,
+    pub file_name: Option<String>,
+}
+
 pub struct WadupInput {
     pos: u64,
     len: u64,
@@ -93,6 +509,104 @@ impl WadupInput {
         }
     }
 
+    pub fn info(&self) -> WadupInputInfo {
+        let encoded = read_host_buffer(unsafe { wadup_input_info_len() }, |buffer, offset, length| unsafe {
+            wadup_input_info_read(buffer, offset, length)
+        });
+        let mut info = WadupInputInfo::default();
+        for (key, value) in decode_pairs(&encoded) {
+            match key.as_str() {
+                "file_path" => info.file_path = value,
+                "offset" => info.offset = value.parse().unwrap_or_default(),
+                "depth" => info.depth = value.parse().unwrap_or_default(),
+                "lineage" => info.lineage = value,
+                "compression" => info.compression = Some(value),
+                "transform" => info.transform = Some(value),
+                "ranges" => info.ranges = value.split_whitespace()
+                    .filter_map(|r| {
+                        let (offset, length) = r.split_once(':')?;
+                        Some((offset.parse().ok()?, length.parse().ok()?))
+                    })
+                    .collect(),
+                "file_type" => info.file_type = Some(value),
+                "mime" => info.mime = Some(value),
+                "parent_module" => info.parent_module = Some(value),
+                "hint" => info.hint = Some(value),
+                "file_name" => info.file_name = Some(value),
+                _ => {},
+            }
+        }
+        info
+    }
+
+    /// Hashes a range of the input on the host, without copying it into the module.
+    pub fn hash(&self, algorithm: WadupHash, range: Range<u64>) -> Vec<u8> {
+        hash(algorithm, -1, range)
+    }
+
+    /// Shannon entropy in bits per byte of each `block_size` block of a range of the input, or of
+    /// the whole range when `block_size` is 0. High entropy blocks are usually compressed or
+    /// encrypted.
+    pub fn entropy(&self, range: Range<u64>, block_size: u64) -> Vec<f64> {
+        entropy(-1, range, block_size)
+    }
+
+    /// Counts of each byte value in each `block_size` block of a range of the input.
+    pub fn histogram(&self, range: Range<u64>, block_size: u64) -> Vec<[u64; 256]> {
+        histogram(-1, range, block_size)
+    }
+
+    /// Decompresses a range of the input on the host into a new output buffer. Returns `None` if
+    /// the data is corrupt or larger than the limit.
+    pub fn decompress(&self, compression: WadupCompression, range: Range<u64>, options: &DecompressOptions) -> Option<WadupOutput> {
+        decompress(compression, -1, range, options)
+    }
+
+    /// Finds every occurrence of the patterns, including overlapping ones, in a range of the input
+    /// without copying it into the module.
+    ///
+    /// ```ignore
+    /// let size = input.seek(SeekFrom::End(0))?;
+    /// for m in input.search(&[b"PK\x03\x04", b"%PDF"], 0..size) {
+    ///     input.carve(m.start, size - m.start);
+    /// }
+    /// ```
+    pub fn search(&self, patterns: &[&[u8]], range: Range<u64>) -> WadupSearch {
+        WadupSearch::patterns(patterns, -1, range)
+    }
+
+    /// Finds successive non-overlapping matches of a regex in a range of the input.
+    pub fn search_regex(&self, pattern: &str, range: Range<u64>) -> WadupSearch {
+        WadupSearch::regex(pattern, -1, range)
+    }
+
+    /// Runs another module on a range of the input and waits for it to finish.
+    pub fn invoke(&self, module: &str, offset: u64, length: u64) -> WadupInvocation {
+        WadupInvocation::run(module, -1, offset, length)
+    }
+
+    /// Tags on the current blob, including those inherited from the blob it was derived from.
+    pub fn tags(&self) -> BTreeMap<String, String> {
+        let encoded = read_host_buffer(unsafe { wadup_input_tags_len() }, |buffer, offset, length| unsafe {
+            wadup_input_tags_read(buffer, offset, length)
+        });
+        decode_pairs(&encoded).into_iter().collect()
+    }
+
+    /// The YARA rules that matched the current input, empty if the host has no rules configured.
+    pub fn yara_matches(&self) -> Vec<WadupYaraMatch> {
+        let encoded = read_host_buffer(unsafe { wadup_input_yara_len() }, |buffer, offset, length| unsafe {
+            wadup_input_yara_read(buffer, offset, length)
+        });
+        WadupYaraMatch::decode(&encoded)
+    }
+
+    /// Sets a tag on the current blob. Blobs carved or submitted afterwards inherit it, and it is
+    /// emitted with every row flushed for the blob.
+    pub fn set_tag(&self, key: &str, value: &str) {
+        unsafe { wadup_input_tag(key.as_ptr(), key.len(), value.as_ptr(), value.len()) }
+    }
+
     pub fn carve(&self, offset: u64, length: u64) {
         unsafe { wadup_input_carve(offset, length) }
     }
@@ -101,6 +615,65 @@ impl WadupInput {
         unsafe { wadup_input_carve(self.pos, length) }
         self.pos += length;
     }
+
+    pub fn carve_to(&self, offset: u64, length: u64, target: &CarveTarget) {
+        let modules = target.modules.join("\n");
+        let hint = target.hint.unwrap_or_default();
+        let file_name = target.file_name.unwrap_or_default();
+        unsafe {
+            wadup_input_carve_to(
+                offset, length,
+                modules.as_ptr(), modules.len(),
+                hint.as_ptr(), hint.len(),
+                file_name.as_ptr(), file_name.len(),
+            )
+        }
+    }
+
+    pub fn carve_from_to(&mut self, length: u64, target: &CarveTarget) {
+        self.carve_to(self.pos, length, target);
+        self.pos += length;
+    }
+
+    /// Carves a range of the input that the host decodes with the transform whenever the carve is
+    /// read. Returns false if the transform is not valid, e.g. a byte swap width other than 2, 4 or
+    /// 8, or the range is not valid for it, e.g. not base64.
+    pub fn carve_transform(&self, range: Range<u64>, transform: WadupTransform, target: &CarveTarget) -> bool {
+        let transform = transform.spec();
+        let modules = target.modules.join("\n");
+        let hint = target.hint.unwrap_or_default();
+        let file_name = target.file_name.unwrap_or_default();
+        let result = unsafe {
+            wadup_input_carve_transform(
+                range.start, range.end.saturating_sub(range.start),
+                transform.as_ptr(), transform.len(),
+                modules.as_ptr(), modules.len(),
+                hint.as_ptr(), hint.len(),
+                file_name.as_ptr(), file_name.len(),
+            )
+        };
+        result == 0
+    }
+
+    /// Carves several ranges of the input as one blob, in order, for data stored in fragments.
+    /// The host reads across the fragments without copying them.
+    pub fn carve_ranges(&self, ranges: &[Range<u64>], target: &CarveTarget) {
+        let encoded = ranges.iter()
+            .flat_map(|r| [r.start, r.end.saturating_sub(r.start)])
+            .flat_map(u64::to_le_bytes)
+            .collect::<Vec<_>>();
+        let modules = target.modules.join("\n");
+        let hint = target.hint.unwrap_or_default();
+        let file_name = target.file_name.unwrap_or_default();
+        unsafe {
+            wadup_input_carve_ranges(
+                encoded.as_ptr(), encoded.len(),
+                modules.as_ptr(), modules.len(),
+                hint.as_ptr(), hint.len(),
+                file_name.as_ptr(), file_name.len(),
+            )
+        }
+    }
 }
 
 impl Read for WadupInput {
@@ -215,6 +788,117 @@ impl WadupColumnFloat64 {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum WadupValue {
+    Str(String),
+    Int64(i64),
+    Float64(f64),
+    None,
+}
+
+impl WadupValue {
+    fn decode(encoded: &str) -> WadupValue {
+        let (kind, value) = encoded.split_at(encoded.len().min(1));
+        match kind {
+            "s" => WadupValue::Str(value.to_owned()),
+            "i" => value.parse().map(WadupValue::Int64).unwrap_or(WadupValue::None),
+            "f" => value.parse().map(WadupValue::Float64).unwrap_or(WadupValue::None),
+            _ => WadupValue::None,
+        }
+    }
+}
+
+/// A row emitted by any module during the run, as seen from a reduce phase.
+#[derive(Debug, Clone, Default)]
+pub struct WadupRow {
+    values: Vec<(String, WadupValue)>,
+}
+
+impl WadupRow {
+    pub fn get(&self, column: &str) -> Option<&WadupValue> {
+        self.values.iter().find(|(c, _)| c == column).map(|(_, v)| v)
+    }
+
+    pub fn get_str(&self, column: &str) -> Option<&str> {
+        match self.get(column) {
+            Some(WadupValue::Str(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_i64(&self, column: &str) -> Option<i64> {
+        match self.get(column) {
+            Some(WadupValue::Int64(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_f64(&self, column: &str) -> Option<f64> {
+        match self.get(column) {
+            Some(WadupValue::Float64(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &WadupValue)> {
+        self.values.iter().map(|(c, v)| (c.as_str(), v))
+    }
+}
+
+/// Iterates over every row emitted so far with the given schema name. Rows are only kept by the
+/// host when at least one module exports `wadup_reduce`.
+pub struct WadupRows {
+    cursor: i32,
+}
+
+impl WadupRows {
+    pub fn new(schema: &str) -> WadupRows {
+        let schema = schema.as_bytes();
+        WadupRows {
+            cursor: unsafe { wadup_rows_query(schema.as_ptr(), schema.len()) }
+        }
+    }
+}
+
+impl Iterator for WadupRows {
+    type Item = WadupRow;
+
+    fn next(&mut self) -> Option<WadupRow> {
+        let len = u32::try_from(unsafe { wadup_rows_next(self.cursor) }).ok()?;
+        let encoded = read_host_buffer(len, |buffer, offset, length| unsafe {
+            wadup_rows_read(self.cursor, buffer, offset, length)
+        });
+        Some(WadupRow {
+            values: decode_pairs(&encoded).into_iter().map(|(c, v)| (c, WadupValue::decode(&v))).collect(),
+        })
+    }
+}
+
+fn read_host_buffer(len: u32, read: impl Fn(*mut u8, u64, usize) -> usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; len as usize];
+    let read = read(buffer.as_mut_ptr(), 0, buffer.len());
+    buffer.truncate(read);
+    buffer
+}
+
+/// Decodes the host's key/value encoding: each key and value is a little endian u32 length
+/// followed by UTF8 bytes.
+fn decode_pairs(mut encoded: &[u8]) -> Vec<(String, String)> {
+    fn next(encoded: &mut &[u8]) -> Option<String> {
+        let (len, rest) = encoded.split_first_chunk::<4>()?;
+        let len = u32::from_le_bytes(*len) as usize;
+        let value = rest.get(..len)?;
+        *encoded = &rest[len..];
+        Some(String::from_utf8_lossy(value).into_owned())
+    }
+
+    let mut pairs = Vec::new();
+    while let (Some(key), Some(value)) = (next(&mut encoded), next(&mut encoded)) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
 const COLUMN_STR: u32 = 1;
 const COLUMN_I64: u32 = 2;
 const COLUMN_F64: u32 = 3;
@@ -223,12 +907,36 @@ const COLUMN_F64: u32 = 3;
 unsafe extern "C" {
     fn wadup_input_read(buffer: *mut u8, offset: u64, length: usize) -> usize;
     fn wadup_input_len() -> u64;
+    fn wadup_input_info_len() -> u32;
+    fn wadup_input_info_read(buffer: *mut u8, offset: u64, length: usize) -> usize;
     fn wadup_input_carve(offset: u64, length: u64);
+    fn wadup_input_tag(key: *const u8, key_length: usize, value: *const u8, value_length: usize);
+    fn wadup_input_tags_len() -> u32;
+    fn wadup_input_tags_read(buffer: *mut u8, offset: u64, length: usize) -> usize;
+    fn wadup_input_yara_len() -> u32;
+    fn wadup_input_yara_read(buffer: *mut u8, offset: u64, length: usize) -> usize;
+
+    fn wadup_config_len() -> u32;
+    fn wadup_config_read(buffer: *mut u8, offset: u64, length: usize) -> usize;
+    fn wadup_input_carve_to(offset: u64, length: u64, modules: *const u8, modules_length: usize, hint: *const u8, hint_length: usize, file_name: *const u8, file_name_length: usize);
+    fn wadup_input_carve_transform(offset: u64, length: u64, transform: *const u8, transform_length: usize, modules: *const u8, modules_length: usize, hint: *const u8, hint_length: usize, file_name: *const u8, file_name_length: usize) -> i32;
+    fn wadup_input_carve_ranges(ranges: *const u8, ranges_length: usize, modules: *const u8, modules_length: usize, hint: *const u8, hint_length: usize, file_name: *const u8, file_name_length: usize);
 
     fn wadup_output_create() -> i32;
     fn wadup_output_read(fd: i32, buffer: *mut u8, offset: u64, length: usize) -> usize;
     fn wadup_output_write(fd: i32, buffer: *const u8, offset: u64, length: usize);
     fn wadup_output_len(fd: i32) -> u64;
+    fn wadup_search_patterns(patterns: *const u8, patterns_length: usize, fd: i32, offset: u64, length: u64) -> i32;
+    fn wadup_search_regex(pattern: *const u8, pattern_length: usize, fd: i32, offset: u64, length: u64) -> i32;
+    fn wadup_search_next(handle: i32, buffer: *mut u8, max: u32) -> u32;
+    fn wadup_decompress(format: u32, fd: i32, offset: u64, length: u64, limit: u64, submit: u32) -> i32;
+    fn wadup_hash(algorithm: u32, fd: i32, offset: u64, length: u64, buffer: *mut u8, buffer_length: usize) -> usize;
+    fn wadup_entropy(fd: i32, offset: u64, length: u64, block_size: u64, buffer: *mut u8, buffer_length: usize) -> u32;
+    fn wadup_histogram(fd: i32, offset: u64, length: u64, block_size: u64, buffer: *mut u8, buffer_length: usize) -> u32;
+    fn wadup_invoke(module: *const u8, module_length: usize, fd: i32, offset: u64, length: u64) -> i32;
+    fn wadup_invocation_len(handle: i32) -> u32;
+    fn wadup_invocation_read(handle: i32, buffer: *mut u8, offset: u64, length: usize) -> usize;
+    fn wadup_output_submit(fd: i32, modules: *const u8, modules_length: usize, hint: *const u8, hint_length: usize, file_name: *const u8, file_name_length: usize);
 
     pub fn wadup_error(error: *const u8, error_length: usize);
 
@@ -238,4 +946,8 @@ unsafe extern "C" {
     fn wadup_metadata_value_i64(schema_index: u32, column_index: u32, value: i64);
     fn wadup_metadata_value_f64(schema_index: u32, column_index: u32, value: f64);
     fn wadup_metadata_flush_row(schema_index: u32);
+
+    fn wadup_rows_query(schema_name: *const u8, schema_length: usize) -> i32;
+    fn wadup_rows_next(cursor: i32) -> i64;
+    fn wadup_rows_read(cursor: i32, buffer: *mut u8, offset: u64, length: usize) -> usize;
 }
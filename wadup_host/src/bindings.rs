@@ -1,59 +1,245 @@
 use std::sync::Arc;
-use wasmtime::{Caller, Linker};
+use wasmtime::{Caller, Linker, Trap};
 use anyhow::{Result, anyhow};
-use uuid::Uuid;
 
-use crate::{carve::Carve, types::DataValue};
+use crate::{carve::{Carve, Concat}, types::{Blob, BlobSource, DataValue}};
 use crate::context::Context;
-use crate::job::{Job, JobInfo, JobOrDie, JobTracking};
+use crate::decompress::{Compression, decompress};
+use crate::hash::HashAlgorithm;
+use crate::mmap::{Derived, contiguous};
+use crate::output::OutputBuffer;
+use crate::entropy::{Histogram, entropy, histogram_at};
+use crate::provenance::{Lineage, Provenance};
+use crate::search::{MATCH_SIZE, Search};
+use crate::transform::{Transform, Transformed};
+use crate::job::invoke;
+use crate::encoding::{decode_list, encode_pairs};
+use crate::rows::{Row, RowCursor};
+use crate::dispatch::{dispatch, dispatch_to, find_modules};
 
-pub fn wadup_read(data: &[u8], mut caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+pub fn wadup_read(data: &(impl BlobSource + ?Sized), mut caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
     let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_read memory not exported"))?;
     let start = usize::try_from(offset).map_err(|_| anyhow!("wadup_read offset u64 to usize conversion failed"))?;
     let length = usize::try_from(length).map_err(|_| anyhow!("wadup_read length u64 to usize conversion failed"))?;
-    let end = std::cmp::min(data.len(), start + length);
-    let input = data.get(start..end).unwrap_or_default();
+    let count = data.len().saturating_sub(start).min(length);
     let buffer = usize::try_from(buffer).map_err(|_| anyhow!("wadup_read buffer u32 to usize conversion failed"))?;
-    memory.write(&mut caller, buffer, input).map_err(|_| anyhow!("wadup_read failed to write memory"))?;
-    let result = u32::try_from(input.len()).map_err(|_| anyhow!("wadup_read result usize to u32 conversion failed"))?;
+    // Read straight into guest memory so sources that aren't contiguous need no intermediate copy
+    let destination = memory.data_mut(&mut caller).get_mut(buffer..buffer.saturating_add(count)).ok_or(anyhow!("wadup_read failed to write memory"))?;
+    let count = data.read_at(start, destination);
+    let result = u32::try_from(count).map_err(|_| anyhow!("wadup_read result usize to u32 conversion failed"))?;
     Ok(result)
 }
 
 pub fn wadup_input_read(caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
     let input = caller.data().input.clone();
-    wadup_read(input.as_ref().as_ref(), caller, buffer, offset, length).map_err(|e| e.context("wadup_input_read"))
+    wadup_read(input.as_ref(), caller, buffer, offset, length).map_err(|e| e.context("wadup_input_read"))
 }
 
 pub fn wadup_input_len(caller: Caller<'_, Context>) -> u64 {
-    caller.data().input.as_ref().as_ref().len() as u64
+    caller.data().input.len() as u64
+}
+
+pub fn wadup_input_info_len(caller: Caller<'_, Context>) -> Result<u32> {
+    let info = encode_pairs(caller.data().job.provenance.info());
+    let result = u32::try_from(info.len()).map_err(|_| anyhow!("wadup_input_info_len result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
+pub fn wadup_input_info_read(caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let info = encode_pairs(caller.data().job.provenance.info());
+    wadup_read(&info, caller, buffer, offset, length).map_err(|e| e.context("wadup_input_info_read"))
+}
+
+/// Sets a tag on the current blob. Blobs carved or submitted afterwards inherit it.
+pub fn wadup_input_tag(mut caller: Caller<'_, Context>, key: u32, key_length: u32, value: u32, value_length: u32) -> Result<()> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_input_tag memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let key = wadup_string_from_buffer(memory, key, key_length).map_err(|e| e.context("wadup_input_tag"))?;
+    let value = wadup_string_from_buffer(memory, value, value_length).map_err(|e| e.context("wadup_input_tag"))?;
+    if key.is_empty() {
+        return Err(anyhow!("wadup_input_tag key is empty"));
+    }
+    caller.data().job.provenance.tags.set(key, value);
+    Ok(())
+}
+
+pub fn wadup_input_tags_len(caller: Caller<'_, Context>) -> Result<u32> {
+    let tags = encode_pairs(caller.data().job.provenance.tags.snapshot());
+    let result = u32::try_from(tags.len()).map_err(|_| anyhow!("wadup_input_tags_len result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
+pub fn wadup_input_tags_read(caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let tags = encode_pairs(caller.data().job.provenance.tags.snapshot());
+    wadup_read(&tags, caller, buffer, offset, length).map_err(|e| e.context("wadup_input_tags_read"))
+}
+
+/// Each match is a "rule" pair followed by its "namespace", "tag" pairs and "string" pairs of the
+/// form identifier:offset:length.
+fn yara_matches(caller: &Caller<'_, Context>) -> Vec<u8> {
+    let mut pairs = Vec::new();
+    for rule_match in &caller.data().job.provenance.yara {
+        pairs.push(("rule", rule_match.rule.clone()));
+        pairs.push(("namespace", rule_match.namespace.clone()));
+        pairs.extend(rule_match.tags.iter().map(|t| ("tag", t.clone())));
+        pairs.extend(rule_match.strings.iter().map(|s| ("string", format!("{}:{}:{}", s.identifier, s.offset, s.length))));
+    }
+    encode_pairs(pairs)
+}
+
+pub fn wadup_input_yara_len(caller: Caller<'_, Context>) -> Result<u32> {
+    let matches = yara_matches(&caller);
+    let result = u32::try_from(matches.len()).map_err(|_| anyhow!("wadup_input_yara_len result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
+pub fn wadup_input_yara_read(caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let matches = yara_matches(&caller);
+    wadup_read(&matches, caller, buffer, offset, length).map_err(|e| e.context("wadup_input_yara_read"))
+}
+
+pub fn wadup_config_len(caller: Caller<'_, Context>) -> Result<u32> {
+    let config = encode_pairs(&caller.data().job.module.config);
+    let result = u32::try_from(config.len()).map_err(|_| anyhow!("wadup_config_len result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
+pub fn wadup_config_read(caller: Caller<'_, Context>, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let config = encode_pairs(&caller.data().job.module.config);
+    wadup_read(&config, caller, buffer, offset, length).map_err(|e| e.context("wadup_config_read"))
 }
 
 pub fn wadup_input_carve(caller: Caller<'_, Context>, offset: u64, length: u64) -> Result<()> {
+    let job = &caller.data().job;
+    let provenance = job.provenance.child(&job.info.module_name, offset);
     let offset = usize::try_from(offset).map_err(|_| anyhow!("wadup_input_carve offset u64 to usize conversion failed"))?;
     let length = usize::try_from(length).map_err(|_| anyhow!("wadup_input_carve length u64 to usize conversion failed"))?;
     let carve = Arc::new(Carve::new(caller.data().input.clone(), offset, length)?);
-    for (module_name, module) in &*caller.data().job.environment.modules {
-        let info = JobInfo {
-            id: Uuid::new_v4(),
-            module_name: module_name.clone(),
-            file_path: None,
-        };
-        let _ = caller.data().job.tracking_sender.send(JobTracking::JobInfo(info.clone()));
-        let _ = caller.data().job.job_sender.send(JobOrDie::Job(Job {
-            info,
-            job_sender: caller.data().job.job_sender.clone(),
-            tracking_sender: caller.data().job.tracking_sender.clone(),
-            environment: caller.data().job.environment.clone(),
-            module: module.clone(),
-            blob: carve.clone(),
-        }));
+    dispatch_carve(&caller, carve, provenance, CarveTarget::default()).map_err(|e| e.context("wadup_input_carve"))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_input_carve_to(
+    mut caller: Caller<'_, Context>,
+    offset: u64,
+    length: u64,
+    modules: u32,
+    modules_length: u32,
+    hint: u32,
+    hint_length: u32,
+    file_name: u32,
+    file_name_length: u32,
+) -> Result<()> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_input_carve_to memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let target = CarveTarget::read(memory, modules, modules_length, hint, hint_length, file_name, file_name_length).map_err(|e| e.context("wadup_input_carve_to"))?;
+
+    let job = &caller.data().job;
+    let provenance = job.provenance.child(&job.info.module_name, offset);
+
+    let offset = usize::try_from(offset).map_err(|_| anyhow!("wadup_input_carve_to offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("wadup_input_carve_to length u64 to usize conversion failed"))?;
+    let carve: Blob = Arc::new(Carve::new(caller.data().input.clone(), offset, length)?);
+    dispatch_carve(&caller, carve, provenance, target).map_err(|e| e.context("wadup_input_carve_to"))
+}
+
+/// Stitches ranges of the input, given as little endian u64 offset and length pairs, into one
+/// blob and dispatches it like `wadup_input_carve_to`.
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_input_carve_ranges(
+    mut caller: Caller<'_, Context>,
+    ranges: u32,
+    ranges_length: u32,
+    modules: u32,
+    modules_length: u32,
+    hint: u32,
+    hint_length: u32,
+    file_name: u32,
+    file_name_length: u32,
+) -> Result<()> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_input_carve_ranges memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let start = usize::try_from(ranges).map_err(|_| anyhow!("wadup_input_carve_ranges ranges u32 to usize conversion failed"))?;
+    let ranges_length = usize::try_from(ranges_length).map_err(|_| anyhow!("wadup_input_carve_ranges ranges_length u32 to usize conversion failed"))?;
+    if ranges_length % 16 != 0 {
+        return Err(anyhow!("wadup_input_carve_ranges ranges_length is not a multiple of 16"));
     }
-    Ok(())
+    let end = start.checked_add(ranges_length).ok_or_else(|| anyhow!("wadup_input_carve_ranges cannot get memory buffer"))?;
+    let encoded = memory.get(start..end).ok_or_else(|| anyhow!("wadup_input_carve_ranges cannot get memory buffer"))?;
+    let ranges = encoded.chunks_exact(16)
+        .map(|range| {
+            let offset = u64::from_le_bytes(range[..8].try_into().unwrap());
+            let length = u64::from_le_bytes(range[8..].try_into().unwrap());
+            (offset, length)
+        })
+        .collect::<Vec<_>>();
+    let target = CarveTarget::read(memory, modules, modules_length, hint, hint_length, file_name, file_name_length).map_err(|e| e.context("wadup_input_carve_ranges"))?;
+
+    let job = &caller.data().job;
+    let mut provenance = job.provenance.child(&job.info.module_name, ranges.first().map(|r| r.0).unwrap_or_default());
+    provenance.lineage = Lineage::Concatenated;
+
+    let fragments = ranges.iter()
+        .map(|(offset, length)| {
+            let offset = usize::try_from(*offset).map_err(|_| anyhow!("wadup_input_carve_ranges offset u64 to usize conversion failed"))?;
+            let length = usize::try_from(*length).map_err(|_| anyhow!("wadup_input_carve_ranges length u64 to usize conversion failed"))?;
+            Ok((offset, length))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let concat: Blob = Arc::new(Concat::new(caller.data().input.clone(), &fragments).map_err(|e| e.context("wadup_input_carve_ranges"))?);
+    provenance.ranges = ranges;
+    dispatch_carve(&caller, concat, provenance, target).map_err(|e| e.context("wadup_input_carve_ranges"))
+}
+
+/// Carves a range of the input read through a transform (`xor:KEY`, `base64`, `hex` or
+/// `byteswap:WIDTH`) and dispatches it like `wadup_input_carve_to`. Returns -1 if the transform is
+/// not valid, such as a byte swap width other than 2, 4 or 8, or the range is not valid for it,
+/// otherwise 0.
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_input_carve_transform(
+    mut caller: Caller<'_, Context>,
+    offset: u64,
+    length: u64,
+    transform: u32,
+    transform_length: u32,
+    modules: u32,
+    modules_length: u32,
+    hint: u32,
+    hint_length: u32,
+    file_name: u32,
+    file_name_length: u32,
+) -> Result<i32> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_input_carve_transform memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let transform = wadup_string_from_buffer(memory, transform, transform_length).map_err(|e| e.context("wadup_input_carve_transform"))?;
+    let Ok(transform) = transform.parse::<Transform>() else {
+        return Ok(-1);
+    };
+    let target = CarveTarget::read(memory, modules, modules_length, hint, hint_length, file_name, file_name_length).map_err(|e| e.context("wadup_input_carve_transform"))?;
+
+    let job = &caller.data().job;
+    let mut provenance = job.provenance.child(&job.info.module_name, offset);
+    provenance.lineage = Lineage::Transformed;
+    provenance.transform = Some(transform.clone());
+
+    let offset = usize::try_from(offset).map_err(|_| anyhow!("wadup_input_carve_transform offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("wadup_input_carve_transform length u64 to usize conversion failed"))?;
+    let Ok(transformed) = Transformed::new(caller.data().input.clone(), offset, length, transform) else {
+        return Ok(-1);
+    };
+    let transformed: Blob = Arc::new(transformed);
+    dispatch_carve(&caller, transformed, provenance, target).map_err(|e| e.context("wadup_input_carve_transform"))?;
+    Ok(0)
 }
 
 pub fn wadup_output_create(caller: Caller<'_, Context>) -> Result<i32> {
     let mut output = caller.data().output.lock().map_err(|_| anyhow!("wadup_output_create unable to lock mutex"))?;
-    output.push(Vec::new());
+    charge_output(&caller, output.len() + 1, 0).map_err(|e| e.context("wadup_output_create"))?;
+    output.push(OutputBuffer::default());
     let result = i32::try_from(output.len() - 1).map_err(|_| anyhow!("wadup_output_create result usize to i32 conversion failed"))?;
     Ok(result)
 }
@@ -63,7 +249,7 @@ pub fn wadup_output_read(caller: Caller<'_, Context>, fd: i32, buffer: u32, offs
     let output = caller.data().output.clone();
     let output = output.lock().map_err(|_| anyhow!("wadup_output_read unable to lock mutex"))?;
     let output = output.get(fd).ok_or_else(|| anyhow!("wadup_output_read fd does not exist"))?;
-    wadup_read(&output, caller, buffer, offset, length).map_err(|e| e.context("wadup_output_read"))
+    wadup_read(output, caller, buffer, offset, length).map_err(|e| e.context("wadup_output_read"))
 }
 
 pub fn wadup_output_write(mut caller: Caller<'_, Context>, fd: i32, buffer: u32, offset: u64, length: u32) -> Result<()> {
@@ -76,12 +262,15 @@ pub fn wadup_output_write(mut caller: Caller<'_, Context>, fd: i32, buffer: u32,
 
     let fd = usize::try_from(fd).map_err(|_| anyhow!("wadup_output_write fd i32 to usize conversion failed"))?;
     let mut output = caller.data().output.lock().map_err(|_| anyhow!("wadup_output_write unable to lock mutex"))?;
+    let count = output.len();
     let output = output.get_mut(fd).ok_or_else(|| anyhow!("wadup_output_write fd does not exist"))?;
-    
-    output.resize(std::cmp::max(offset + length, output.len()), 0);
-    let output = &mut output[offset..offset+length];
+
+    // Charge any growth before resizing, so a write far past the end fails instead of allocating
+    let end = offset.checked_add(length).ok_or_else(|| anyhow!("wadup_output_write offset out of range"))?;
     let memory = memory.get(buffer..buffer+length).ok_or_else(|| anyhow!("wadup_output_write cannot get memory buffer"))?;
-    output.copy_from_slice(memory);
+    charge_output(&caller, count, end.saturating_sub(output.len())).map_err(|e| e.context("wadup_output_write"))?;
+    let spill = caller.data().job.environment.config.output_spill();
+    output.write_at(offset, memory, spill).map_err(|e| e.context("wadup_output_write"))?;
 
     Ok(())
 }
@@ -94,12 +283,396 @@ pub fn wadup_output_len(caller: Caller<'_, Context>, fd: i32) -> Result<u64> {
     Ok(result)
 }
 
+/// Submits a snapshot of an output buffer as a new blob derived from the current input. Targets and
+/// hints work as for `wadup_input_carve_to`; the blob's offset is always zero.
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_output_submit(
+    mut caller: Caller<'_, Context>,
+    fd: i32,
+    modules: u32,
+    modules_length: u32,
+    hint: u32,
+    hint_length: u32,
+    file_name: u32,
+    file_name_length: u32,
+) -> Result<()> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_output_submit memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let target = CarveTarget::read(memory, modules, modules_length, hint, hint_length, file_name, file_name_length).map_err(|e| e.context("wadup_output_submit"))?;
+
+    let fd = usize::try_from(fd).map_err(|_| anyhow!("wadup_output_submit fd i32 to usize conversion failed"))?;
+    let output = caller.data().output.lock().map_err(|_| anyhow!("wadup_output_submit unable to lock mutex"))?;
+    let buffer = output.get(fd).ok_or_else(|| anyhow!("wadup_output_submit fd does not exist"))?.clone();
+    drop(output);
+
+    let job = &caller.data().job;
+    let blob = output_blob(&caller, buffer).map_err(|e| e.context("wadup_output_submit"))?;
+    let mut provenance = job.provenance.child(&job.info.module_name, 0);
+    provenance.lineage = Lineage::Submitted;
+    dispatch_carve(&caller, blob, provenance, target).map_err(|e| e.context("wadup_output_submit"))
+}
+
+/// Runs another module synchronously on a range of the input (`fd` -1) or of an output buffer,
+/// charging the fuel it uses to the caller. Its output buffers are handed over to the caller, and
+/// the returned handle reads back their fds, its tags and any error it raised.
+pub fn wadup_invoke(mut caller: Caller<'_, Context>, module: u32, module_length: u32, fd: i32, offset: u64, length: u64) -> Result<i32> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_invoke memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let module = wadup_string_from_buffer(memory, module, module_length).map_err(|e| e.context("wadup_invoke"))?;
+    let job = caller.data().job.clone();
+    let module = find_modules(&job.environment, &[&module]).map_err(|e| e.context("wadup_invoke"))?[0].clone();
+
+    let start = usize::try_from(offset).map_err(|_| anyhow!("wadup_invoke offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("wadup_invoke length u64 to usize conversion failed"))?;
+    // A snapshot of an output buffer is shared rather than copied, spilled or not
+    let source = source_blob(&caller, fd).map_err(|e| e.context("wadup_invoke"))?;
+    let blob: Blob = Arc::new(Carve::new(source, start, length).map_err(|e| e.context("wadup_invoke"))?);
+    let provenance = if fd < 0 {
+        job.provenance.child(&job.info.module_name, offset)
+    } else {
+        // An output buffer is not part of the input, so the offset says nothing about where the
+        // blob came from
+        let mut provenance = job.provenance.child(&job.info.module_name, 0);
+        provenance.lineage = Lineage::Submitted;
+        provenance
+    };
+
+    let fuel = caller.get_fuel()?;
+    let usage = caller.data().usage.clone();
+    let invocation = invoke(&job, module, blob, provenance, fuel, usage).map_err(|e| e.context("wadup_invoke"))?;
+    caller.set_fuel(fuel - invocation.fuel_used)?;
+
+    let mut result = Vec::new();
+    let mut output = caller.data().output.lock().map_err(|_| anyhow!("wadup_invoke unable to lock mutex"))?;
+    // The invoked module's output bytes were charged as it wrote them
+    charge_output(&caller, output.len() + invocation.outputs.len(), 0).map_err(|e| e.context("wadup_invoke"))?;
+    for buffer in invocation.outputs {
+        output.push(buffer);
+        result.push(("output".to_owned(), (output.len() - 1).to_string()));
+    }
+    drop(output);
+    if let Some(error) = invocation.error {
+        result.push(("error".to_owned(), error));
+    }
+    result.extend(invocation.tags.into_iter().map(|(k, v)| (format!("tag.{}", k), v)));
+
+    let mut invocations = caller.data().invocations.lock().map_err(|_| anyhow!("wadup_invoke unable to lock mutex"))?;
+    invocations.push(encode_pairs(result));
+    let handle = i32::try_from(invocations.len() - 1).map_err(|_| anyhow!("wadup_invoke result usize to i32 conversion failed"))?;
+    Ok(handle)
+}
+
+pub fn wadup_invocation_len(caller: Caller<'_, Context>, handle: i32) -> Result<u32> {
+    let handle = usize::try_from(handle).map_err(|_| anyhow!("wadup_invocation_len handle i32 to usize conversion failed"))?;
+    let invocations = caller.data().invocations.lock().map_err(|_| anyhow!("wadup_invocation_len unable to lock mutex"))?;
+    let invocation = invocations.get(handle).ok_or_else(|| anyhow!("wadup_invocation_len handle does not exist"))?;
+    let result = u32::try_from(invocation.len()).map_err(|_| anyhow!("wadup_invocation_len result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
+pub fn wadup_invocation_read(caller: Caller<'_, Context>, handle: i32, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let handle = usize::try_from(handle).map_err(|_| anyhow!("wadup_invocation_read handle i32 to usize conversion failed"))?;
+    let invocations = caller.data().invocations.clone();
+    let invocations = invocations.lock().map_err(|_| anyhow!("wadup_invocation_read unable to lock mutex"))?;
+    let invocation = invocations.get(handle).ok_or_else(|| anyhow!("wadup_invocation_read handle does not exist"))?;
+    wadup_read(invocation, caller, buffer, offset, length).map_err(|e| e.context("wadup_invocation_read"))
+}
+
+/// Hashes a range of the input (`fd` -1) or of an output buffer, writing up to `buffer_length`
+/// bytes of the digest and returning its full length.
+pub fn wadup_hash(caller: Caller<'_, Context>, algorithm: u32, fd: i32, offset: u64, length: u64, buffer: u32, buffer_length: u32) -> Result<u32> {
+    let algorithm = HashAlgorithm::from_id(algorithm).map_err(|e| e.context("wadup_hash"))?;
+    let start = usize::try_from(offset).map_err(|_| anyhow!("wadup_hash offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("wadup_hash length u64 to usize conversion failed"))?;
+    let end = start.checked_add(length).ok_or_else(|| anyhow!("wadup_hash range out of bounds"))?;
+
+    let source = source_blob(&caller, fd).map_err(|e| e.context("wadup_hash"))?;
+    if end > source.len() {
+        return Err(anyhow!("wadup_hash range out of bounds"));
+    }
+    let range: Blob = Arc::new(Carve::new(source, start, length).map_err(|e| e.context("wadup_hash"))?);
+    let digest = if algorithm.is_fuzzy() {
+        // Similarity digests need one slice, so a range that isn't contiguous is copied and
+        // counted against the mapped budget
+        let job = &caller.data().job;
+        let range = contiguous(range, job.environment.mapped.clone(), job.tracking_sender.clone()).map_err(|e| e.context("wadup_hash"))?;
+        algorithm.digest(range.as_slice().unwrap_or_default())
+    } else {
+        algorithm.digest_at(&*range)
+    };
+
+    let result = u32::try_from(digest.len()).map_err(|_| anyhow!("wadup_hash result usize to u32 conversion failed"))?;
+    wadup_read(&digest, caller, buffer, 0, buffer_length).map_err(|e| e.context("wadup_hash"))?;
+    Ok(result)
+}
+
+/// Runs `encode` on the histogram of each `block_size` block of a range, the whole range being one
+/// block when `block_size` is 0, for as many blocks as fit in the buffer at `item_size` bytes each,
+/// and returns the total number of blocks. The bytes read are charged to the job's fuel.
+#[allow(clippy::too_many_arguments)]
+fn histogram_blocks(
+    mut caller: Caller<'_, Context>,
+    fd: i32,
+    offset: u64,
+    length: u64,
+    block_size: u64,
+    buffer: u32,
+    buffer_length: u32,
+    item_size: usize,
+    encode: impl Fn(&Histogram, &mut [u8]),
+) -> Result<u32> {
+    let data = source_blob(&caller, fd)?;
+    let (start, end) = search_range(offset, length)?;
+    if end > data.len() {
+        return Err(anyhow!("range out of bounds"));
+    }
+    let block_size = usize::try_from(block_size).map_err(|_| anyhow!("block_size u64 to usize conversion failed"))?;
+    let block_size = if block_size == 0 { (end - start).max(1) } else { block_size };
+    let count = (end - start).div_ceil(block_size);
+    let result = u32::try_from(count).map_err(|_| anyhow!("too many blocks"))?;
+
+    let buffer_length = usize::try_from(buffer_length).map_err(|_| anyhow!("buffer_length u32 to usize conversion failed"))?;
+    let encoded = count.min(buffer_length / item_size);
+    let scanned = encoded.saturating_mul(block_size).min(end - start);
+    let fuel = caller.get_fuel()?;
+    caller.set_fuel(fuel.checked_sub(scanned as u64).ok_or(Trap::OutOfFuel)?)?;
+
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("memory not exported"))?;
+    let buffer = usize::try_from(buffer).map_err(|_| anyhow!("buffer u32 to usize conversion failed"))?;
+    let destination = memory.data_mut(&mut caller).get_mut(buffer..buffer.saturating_add(encoded * item_size)).ok_or(anyhow!("failed to write memory"))?;
+    for (index, item) in destination.chunks_exact_mut(item_size).enumerate() {
+        let block_start = start + index * block_size;
+        encode(&histogram_at(data.as_ref(), block_start, (block_start + block_size).min(end)), item);
+    }
+    Ok(result)
+}
+
+/// Computes the Shannon entropy of each `block_size` block of a range of the input (`fd` -1) or
+/// of an output buffer, the whole range being one block when `block_size` is 0. Writes a little
+/// endian f64 per block, as many as fit in the buffer, and returns the number of blocks.
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_entropy(caller: Caller<'_, Context>, fd: i32, offset: u64, length: u64, block_size: u64, buffer: u32, buffer_length: u32) -> Result<u32> {
+    histogram_blocks(caller, fd, offset, length, block_size, buffer, buffer_length, 8, |histogram, item| {
+        item.copy_from_slice(&entropy(histogram).to_le_bytes());
+    }).map_err(|e| e.context("wadup_entropy"))
+}
+
+/// Counts each byte value in each `block_size` block of a range, like `wadup_entropy`. Writes 256
+/// little endian u64 counts per block, as many as fit in the buffer, and returns the number of
+/// blocks.
+#[allow(clippy::too_many_arguments)]
+pub fn wadup_histogram(caller: Caller<'_, Context>, fd: i32, offset: u64, length: u64, block_size: u64, buffer: u32, buffer_length: u32) -> Result<u32> {
+    histogram_blocks(caller, fd, offset, length, block_size, buffer, buffer_length, 256 * 8, |histogram, item| {
+        for (count, bytes) in histogram.iter().zip(item.chunks_exact_mut(8)) {
+            bytes.copy_from_slice(&count.to_le_bytes());
+        }
+    }).map_err(|e| e.context("wadup_histogram"))
+}
+
+/// Decompresses a range of the input (`fd` -1) or of an output buffer into a new output buffer,
+/// returning its fd, or -1 if the data is corrupt or decompresses to more than the limit. The
+/// limit is the smallest of `limit` (ignored when 0), the configured decompression limit and the
+/// job's remaining output quota. With `submit` set the result is also routed as a blob derived
+/// from the current input.
+pub fn wadup_decompress(caller: Caller<'_, Context>, format: u32, fd: i32, offset: u64, length: u64, limit: u64, submit: u32) -> Result<i32> {
+    let compression = Compression::from_id(format).map_err(|e| e.context("wadup_decompress"))?;
+    let start = usize::try_from(offset).map_err(|_| anyhow!("wadup_decompress offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("wadup_decompress length u64 to usize conversion failed"))?;
+    let end = start.checked_add(length).ok_or_else(|| anyhow!("wadup_decompress range out of bounds"))?;
+
+    let job = &caller.data().job;
+    let configured = job.environment.config.decompress_limit.0;
+    let remaining = caller.data().usage.lock().map_err(|_| anyhow!("wadup_decompress unable to lock mutex"))?
+        .output_bytes_remaining(&job.environment.config.quotas);
+    let limit = if limit == 0 { configured } else { limit.min(configured) }.min(remaining);
+    let limit = usize::try_from(limit).map_err(|_| anyhow!("wadup_decompress limit u64 to usize conversion failed"))?;
+
+    let decompressed = if fd < 0 {
+        let input = caller.data().input.clone();
+        let range = input.range(start, end).ok_or_else(|| anyhow!("wadup_decompress range out of bounds"))?;
+        decompress(compression, &range, limit)
+    } else {
+        let fd = usize::try_from(fd).map_err(|_| anyhow!("wadup_decompress fd i32 to usize conversion failed"))?;
+        let output = caller.data().output.lock().map_err(|_| anyhow!("wadup_decompress unable to lock mutex"))?;
+        let output = output.get(fd).ok_or_else(|| anyhow!("wadup_decompress fd does not exist"))?;
+        let range = output.range(start, end).ok_or_else(|| anyhow!("wadup_decompress range out of bounds"))?;
+        decompress(compression, &range, limit)
+    };
+    let Ok(decompressed) = decompressed else {
+        return Ok(-1);
+    };
+
+    let buffers = caller.data().output.lock().map_err(|_| anyhow!("wadup_decompress unable to lock mutex"))?.len() + 1;
+    charge_output(&caller, buffers, decompressed.len()).map_err(|e| e.context("wadup_decompress"))?;
+
+    let spill = job.environment.config.output_spill();
+    let buffer = OutputBuffer::new(decompressed, spill).map_err(|e| e.context("wadup_decompress"))?;
+    if submit != 0 {
+        // An offset into an output buffer says nothing about where in the input the data came from
+        let mut provenance = job.provenance.child(&job.info.module_name, if fd < 0 { offset } else { 0 });
+        provenance.lineage = Lineage::Decompressed(compression);
+        let blob = output_blob(&caller, buffer.clone()).map_err(|e| e.context("wadup_decompress"))?;
+        dispatch_carve(&caller, blob, provenance, CarveTarget::default()).map_err(|e| e.context("wadup_decompress"))?;
+    }
+
+    let mut output = caller.data().output.lock().map_err(|_| anyhow!("wadup_decompress unable to lock mutex"))?;
+    output.push(buffer);
+    let result = i32::try_from(output.len() - 1).map_err(|_| anyhow!("wadup_decompress result usize to i32 conversion failed"))?;
+    Ok(result)
+}
+
+/// Where a carved or submitted blob goes and what it is called. Module names are separated by
+/// newlines; with no names the blob is routed as usual.
+#[derive(Default)]
+struct CarveTarget {
+    modules: String,
+    hint: String,
+    file_name: String,
+}
+
+impl CarveTarget {
+    fn read(memory: &[u8], modules: u32, modules_length: u32, hint: u32, hint_length: u32, file_name: u32, file_name_length: u32) -> Result<CarveTarget> {
+        Ok(CarveTarget {
+            modules: wadup_string_from_buffer(memory, modules, modules_length)?,
+            hint: wadup_string_from_buffer(memory, hint, hint_length)?,
+            file_name: wadup_string_from_buffer(memory, file_name, file_name_length)?,
+        })
+    }
+}
+
+/// Dispatches a blob the job carved, submitted or decompressed to its target modules. The modules
+/// are looked up before the blob is counted against the carve quota, so naming an unknown module
+/// fails without using it up.
+fn dispatch_carve(caller: &Caller<'_, Context>, blob: Blob, mut provenance: Provenance, target: CarveTarget) -> Result<()> {
+    let job = &caller.data().job;
+    let module_names = target.modules.lines().map(str::trim).filter(|m| !m.is_empty()).collect::<Vec<_>>();
+    let targets = if module_names.is_empty() { None } else { Some(find_modules(&job.environment, &module_names)?) };
+    provenance.hint = Some(target.hint).filter(|h| !h.is_empty());
+    provenance.file_name = Some(target.file_name).filter(|f| !f.is_empty());
+
+    charge_carve(caller)?;
+    match targets {
+        Some(targets) => dispatch_to(&job.environment, &job.job_sender, &job.tracking_sender, blob, provenance, &targets),
+        None => dispatch(&job.environment, &job.job_sender, &job.tracking_sender, blob, provenance),
+    }
+    Ok(())
+}
+
+/// A snapshot of an output buffer as a blob, sharing its data rather than copying it. A buffer
+/// still in memory is counted against the mapped budget; a spilled one is on disk.
+fn output_blob(caller: &Caller<'_, Context>, buffer: OutputBuffer) -> Result<Blob> {
+    let job = &caller.data().job;
+    Ok(match buffer {
+        OutputBuffer::Memory(data) => Arc::new(Derived::new(data, job.environment.mapped.clone(), job.tracking_sender.clone())?),
+        spilled => Arc::new(spilled),
+    })
+}
+
+/// Counts a blob the job is about to dispatch against its carve quota.
+fn charge_carve(caller: &Caller<'_, Context>) -> Result<()> {
+    let mut usage = caller.data().usage.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+    usage.carve(&caller.data().job.environment.config.quotas)
+}
+
+/// Checks the number of output buffers the job would have and charges `grown` more output bytes.
+fn charge_output(caller: &Caller<'_, Context>, buffers: usize, grown: usize) -> Result<()> {
+    let quotas = &caller.data().job.environment.config.quotas;
+    let mut usage = caller.data().usage.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+    usage.output_buffers(quotas, buffers)?;
+    usage.output_bytes(quotas, grown as u64)
+}
+
+/// The input (`fd` -1) or a snapshot of an output buffer, for host services that outlive the call.
+fn source_blob(caller: &Caller<'_, Context>, fd: i32) -> Result<Blob> {
+    if fd < 0 {
+        return Ok(caller.data().input.clone());
+    }
+    let fd = usize::try_from(fd).map_err(|_| anyhow!("fd i32 to usize conversion failed"))?;
+    let output = caller.data().output.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+    let output = output.get(fd).ok_or_else(|| anyhow!("fd does not exist"))?;
+    Ok(Arc::new(output.clone()))
+}
+
+/// The input or an output buffer as a searchable blob, copied into one piece, and counted against
+/// the mapped budget, if it isn't contiguous.
+fn search_blob(caller: &Caller<'_, Context>, fd: i32) -> Result<Blob> {
+    let job = &caller.data().job;
+    contiguous(source_blob(caller, fd)?, job.environment.mapped.clone(), job.tracking_sender.clone())
+}
+
+fn search_range(offset: u64, length: u64) -> Result<(usize, usize)> {
+    let start = usize::try_from(offset).map_err(|_| anyhow!("offset u64 to usize conversion failed"))?;
+    let length = usize::try_from(length).map_err(|_| anyhow!("length u64 to usize conversion failed"))?;
+    let end = start.checked_add(length).ok_or_else(|| anyhow!("range out of bounds"))?;
+    Ok((start, end))
+}
+
+fn add_search(caller: &Caller<'_, Context>, search: Search) -> Result<i32> {
+    let mut searches = caller.data().searches.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+    searches.push(search);
+    i32::try_from(searches.len() - 1).map_err(|_| anyhow!("result usize to i32 conversion failed"))
+}
+
+/// Starts a search for every occurrence of a list of byte patterns (each a little endian u32
+/// length followed by the bytes) in a range of the input or of an output buffer.
+pub fn wadup_search_patterns(mut caller: Caller<'_, Context>, patterns: u32, patterns_length: u32, fd: i32, offset: u64, length: u64) -> Result<i32> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_search_patterns memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let start = usize::try_from(patterns).map_err(|_| anyhow!("wadup_search_patterns patterns u32 to usize conversion failed"))?;
+    let end = start + usize::try_from(patterns_length).map_err(|_| anyhow!("wadup_search_patterns patterns_length u32 to usize conversion failed"))?;
+    let encoded = memory.get(start..end).ok_or_else(|| anyhow!("wadup_search_patterns cannot get memory buffer"))?;
+    let patterns = decode_list(encoded).map_err(|e| e.context("wadup_search_patterns"))?;
+
+    let data = search_blob(&caller, fd).map_err(|e| e.context("wadup_search_patterns"))?;
+    let (start, end) = search_range(offset, length).map_err(|e| e.context("wadup_search_patterns"))?;
+    let search = Search::patterns(data, start, end, &patterns).map_err(|e| e.context("wadup_search_patterns"))?;
+    add_search(&caller, search).map_err(|e| e.context("wadup_search_patterns"))
+}
+
+/// Starts a regex search in a range of the input or of an output buffer.
+pub fn wadup_search_regex(mut caller: Caller<'_, Context>, pattern: u32, pattern_length: u32, fd: i32, offset: u64, length: u64) -> Result<i32> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_search_regex memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let pattern = wadup_string_from_buffer(memory, pattern, pattern_length).map_err(|e| e.context("wadup_search_regex"))?;
+    let data = search_blob(&caller, fd).map_err(|e| e.context("wadup_search_regex"))?;
+    let (start, end) = search_range(offset, length).map_err(|e| e.context("wadup_search_regex"))?;
+    let search = Search::regex(data, start, end, &pattern).map_err(|e| e.context("wadup_search_regex"))?;
+    add_search(&caller, search).map_err(|e| e.context("wadup_search_regex"))
+}
+
+/// Writes up to `max` further matches of a search to the buffer, returning how many were written.
+/// No more matches are found than fit in guest memory after the buffer, and the bytes scanned are
+/// charged to the job's fuel.
+pub fn wadup_search_next(mut caller: Caller<'_, Context>, handle: i32, buffer: u32, max: u32) -> Result<u32> {
+    let handle = usize::try_from(handle).map_err(|_| anyhow!("wadup_search_next handle i32 to usize conversion failed"))?;
+    let max = usize::try_from(max).map_err(|_| anyhow!("wadup_search_next max u32 to usize conversion failed"))?;
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_search_next memory not exported"))?;
+    let buffer_start = usize::try_from(buffer).map_err(|_| anyhow!("wadup_search_next buffer u32 to usize conversion failed"))?;
+    let max = max.min(memory.data_size(&caller).saturating_sub(buffer_start) / MATCH_SIZE);
+
+    let mut searches = caller.data().searches.lock().map_err(|_| anyhow!("wadup_search_next unable to lock mutex"))?;
+    let search = searches.get_mut(handle).ok_or_else(|| anyhow!("wadup_search_next handle does not exist"))?;
+    let (batch, scanned) = search.next_batch(max);
+    drop(searches);
+
+    let fuel = caller.get_fuel()?;
+    caller.set_fuel(fuel.checked_sub(scanned as u64).ok_or(Trap::OutOfFuel)?)?;
+
+    let length = u32::try_from(batch.len()).map_err(|_| anyhow!("wadup_search_next length usize to u32 conversion failed"))?;
+    wadup_read(&batch, caller, buffer, 0, length).map_err(|e| e.context("wadup_search_next"))?;
+    let result = u32::try_from(batch.len() / MATCH_SIZE).map_err(|_| anyhow!("wadup_search_next result usize to u32 conversion failed"))?;
+    Ok(result)
+}
+
 pub fn wadup_string_from_buffer(memory: &[u8], buffer: u32, length: u32) -> Result<String> {
     let buffer = usize::try_from(buffer).map_err(|_| anyhow!("wadup_string_from_buffer buffer u32 to usize conversion failed"))?;
     let length = usize::try_from(length).map_err(|_| anyhow!("wadup_string_from_buffer length u32 to usize conversion failed"))?;
 
-    let mut value = Vec::<u8>::new();
-    value.resize(length, 0);
+    let mut value = vec![0u8; length];
     let value_buffer = value.as_mut_slice();
     let memory = memory.get(buffer..buffer+length).ok_or_else(|| anyhow!("wadup_string_from_buffer cannot get memory buffer"))?;
     value_buffer.copy_from_slice(memory);
@@ -123,7 +696,7 @@ pub fn wadup_metadata_schema(mut caller: Caller<'_, Context>, schema_name: u32,
     let schema_name = wadup_string_from_buffer(memory, schema_name, schema_length).map_err(|e| e.context("wadup_metadata_schema"))?;
     let mut schema = caller.data().schema.lock().map_err(|_| anyhow!("wadup_metadata_schema failed to get schema lock"))?;
     let schema_index = schema.get_by_left(&schema_name).map(|v| v.to_owned()).unwrap_or_else(|| {
-        let next_schema_index = schema.iter().map(|(_, v)| v.to_owned()).max().unwrap_or(0u32) + 1;
+        let next_schema_index = schema.right_values().map(|v| v.to_owned()).max().unwrap_or(0u32) + 1;
         schema.insert(schema_name, next_schema_index);
         next_schema_index
     });
@@ -138,7 +711,7 @@ fn wadup_metadata_column(mut caller: Caller<'_, Context>, schema_index: u32, col
     let mut column = caller.data().column.lock().map_err(|_| anyhow!("wadup_metadata_column failed to get column lock"))?;
     let column = column.entry(schema_index).or_default();
     let column_index = column.get(&column_name).map(|v| v.to_owned()).unwrap_or_else(|| {
-        let next_column_index = column.iter().map(|(_, v)| v.to_owned()).max().unwrap_or(0u32) + 1;
+        let next_column_index = column.values().map(|v| v.to_owned()).max().unwrap_or(0u32) + 1;
         column.insert(column_name, next_column_index);
         next_column_index
     });
@@ -174,21 +747,96 @@ pub fn wadup_metadata_flush_row(caller: Caller<'_, Context>, schema_index: u32)
     let metadata = caller.data().metadata.lock().map_err(|_| anyhow!("wadup_metadata_flush_row failed to get metadata lock"))?;
     let schema_name = schema.get_by_right(&schema_index).ok_or_else(|| anyhow!("wadup_metadata_flush_row schema index not found"))?;
     let column = column.get(&schema_index).ok_or_else(|| anyhow!("wadup_metadata_flush_row schema index not found"))?;
-    for (column_name, column_index) in column {
-        let value = metadata.get(&(schema_index, column_index.to_owned())).unwrap_or(&DataValue::NoneValue);
-        println!("DATA: {} {} {:?}", schema_name, column_name, value);
+    let mut columns = column.iter().collect::<Vec<_>>();
+    columns.sort_by_key(|(_, column_index)| **column_index);
+    let values = columns.into_iter()
+        .map(|(column_name, column_index)| {
+            let value = metadata.get(&(schema_index, column_index.to_owned())).unwrap_or(&DataValue::NoneValue);
+            (column_name.clone(), value.clone())
+        })
+        .collect();
+    let job = &caller.data().job;
+    let mut usage = caller.data().usage.lock().map_err(|_| anyhow!("wadup_metadata_flush_row unable to lock mutex"))?;
+    usage.row(&job.environment.config.quotas, schema_name).map_err(|e| e.context("wadup_metadata_flush_row"))?;
+    drop(usage);
+    job.environment.rows.emit(Row {
+        schema: schema_name.clone(),
+        values,
+        tags: job.provenance.tags.snapshot(),
+    }, &job.tracking_sender)
+}
+
+pub fn wadup_rows_query(mut caller: Caller<'_, Context>, schema_name: u32, schema_length: u32) -> Result<i32> {
+    let memory = caller.get_export("memory").and_then(|v| v.into_memory()).ok_or(anyhow!("wadup_rows_query memory not exported"))?;
+    let memory = memory.data(&caller);
+
+    let schema_name = wadup_string_from_buffer(memory, schema_name, schema_length).map_err(|e| e.context("wadup_rows_query"))?;
+    let rows = caller.data().job.environment.rows.query(&schema_name).map_err(|e| e.context("wadup_rows_query"))?;
+    let mut cursors = caller.data().cursors.lock().map_err(|_| anyhow!("wadup_rows_query unable to lock mutex"))?;
+    cursors.push(RowCursor { rows, position: None, current: Vec::new() });
+    let result = i32::try_from(cursors.len() - 1).map_err(|_| anyhow!("wadup_rows_query result usize to i32 conversion failed"))?;
+    Ok(result)
+}
+
+/// Moves the cursor to the next row, returning the length of its encoding or -1 past the last row.
+pub fn wadup_rows_next(caller: Caller<'_, Context>, cursor: i32) -> Result<i64> {
+    let cursor = usize::try_from(cursor).map_err(|_| anyhow!("wadup_rows_next cursor i32 to usize conversion failed"))?;
+    let mut cursors = caller.data().cursors.lock().map_err(|_| anyhow!("wadup_rows_next unable to lock mutex"))?;
+    let cursor = cursors.get_mut(cursor).ok_or_else(|| anyhow!("wadup_rows_next cursor does not exist"))?;
+    let position = cursor.position.map(|p| p + 1).unwrap_or(0);
+    cursor.position = Some(position);
+    match cursor.rows.get(position) {
+        Some(row) => {
+            cursor.current = row.encode();
+            let result = i64::try_from(cursor.current.len()).map_err(|_| anyhow!("wadup_rows_next result usize to i64 conversion failed"))?;
+            Ok(result)
+        },
+        None => {
+            cursor.current.clear();
+            Ok(-1)
+        },
     }
-    Ok(())
+}
+
+pub fn wadup_rows_read(caller: Caller<'_, Context>, cursor: i32, buffer: u32, offset: u64, length: u32) -> Result<u32> {
+    let cursor = usize::try_from(cursor).map_err(|_| anyhow!("wadup_rows_read cursor i32 to usize conversion failed"))?;
+    let cursors = caller.data().cursors.clone();
+    let cursors = cursors.lock().map_err(|_| anyhow!("wadup_rows_read unable to lock mutex"))?;
+    let cursor = cursors.get(cursor).ok_or_else(|| anyhow!("wadup_rows_read cursor does not exist"))?;
+    wadup_read(&cursor.current, caller, buffer, offset, length).map_err(|e| e.context("wadup_rows_read"))
 }
 
 pub fn add_to_linker(linker : &mut Linker<Context>) -> Result<()> {
     linker.func_wrap("host", "wadup_input_read", wadup_input_read)?;
     linker.func_wrap("host", "wadup_input_len", wadup_input_len)?;
+    linker.func_wrap("host", "wadup_input_info_len", wadup_input_info_len)?;
+    linker.func_wrap("host", "wadup_input_info_read", wadup_input_info_read)?;
+    linker.func_wrap("host", "wadup_input_tag", wadup_input_tag)?;
+    linker.func_wrap("host", "wadup_input_tags_len", wadup_input_tags_len)?;
+    linker.func_wrap("host", "wadup_input_tags_read", wadup_input_tags_read)?;
+    linker.func_wrap("host", "wadup_input_yara_len", wadup_input_yara_len)?;
+    linker.func_wrap("host", "wadup_input_yara_read", wadup_input_yara_read)?;
+    linker.func_wrap("host", "wadup_config_len", wadup_config_len)?;
+    linker.func_wrap("host", "wadup_config_read", wadup_config_read)?;
     linker.func_wrap("host", "wadup_input_carve", wadup_input_carve)?;
+    linker.func_wrap("host", "wadup_input_carve_to", wadup_input_carve_to)?;
+    linker.func_wrap("host", "wadup_input_carve_ranges", wadup_input_carve_ranges)?;
+    linker.func_wrap("host", "wadup_input_carve_transform", wadup_input_carve_transform)?;
     linker.func_wrap("host", "wadup_output_create", wadup_output_create)?;
     linker.func_wrap("host", "wadup_output_read", wadup_output_read)?;
     linker.func_wrap("host", "wadup_output_write", wadup_output_write)?;
     linker.func_wrap("host", "wadup_output_len", wadup_output_len)?;
+    linker.func_wrap("host", "wadup_output_submit", wadup_output_submit)?;
+    linker.func_wrap("host", "wadup_invoke", wadup_invoke)?;
+    linker.func_wrap("host", "wadup_hash", wadup_hash)?;
+    linker.func_wrap("host", "wadup_entropy", wadup_entropy)?;
+    linker.func_wrap("host", "wadup_histogram", wadup_histogram)?;
+    linker.func_wrap("host", "wadup_decompress", wadup_decompress)?;
+    linker.func_wrap("host", "wadup_search_patterns", wadup_search_patterns)?;
+    linker.func_wrap("host", "wadup_search_regex", wadup_search_regex)?;
+    linker.func_wrap("host", "wadup_search_next", wadup_search_next)?;
+    linker.func_wrap("host", "wadup_invocation_len", wadup_invocation_len)?;
+    linker.func_wrap("host", "wadup_invocation_read", wadup_invocation_read)?;
     linker.func_wrap("host", "wadup_error", wadup_error)?;
     linker.func_wrap("host", "wadup_metadata_schema", wadup_metadata_schema)?;
     linker.func_wrap("host", "wadup_metadata_column", wadup_metadata_column)?;
@@ -196,5 +844,8 @@ pub fn add_to_linker(linker : &mut Linker<Context>) -> Result<()> {
     linker.func_wrap("host", "wadup_metadata_value_i64", wadup_metadata_value_i64)?;
     linker.func_wrap("host", "wadup_metadata_value_f64", wadup_metadata_value_f64)?;
     linker.func_wrap("host", "wadup_metadata_flush_row", wadup_metadata_flush_row)?;
+    linker.func_wrap("host", "wadup_rows_query", wadup_rows_query)?;
+    linker.func_wrap("host", "wadup_rows_next", wadup_rows_next)?;
+    linker.func_wrap("host", "wadup_rows_read", wadup_rows_read)?;
     Ok(())
 }
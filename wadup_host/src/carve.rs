@@ -1,4 +1,4 @@
-use crate::types::Blob;
+use crate::types::{Blob, BlobSource};
 use anyhow::{Result, anyhow};
 
 pub struct Carve {
@@ -7,21 +7,156 @@ pub struct Carve {
     pub len: usize,
 }
 
+/// The innermost source behind a blob and the offset in it of a position in the blob, so that
+/// carves of carves refer to the root source directly rather than keeping a chain of parents.
+pub fn flatten(data: Blob, offset: usize) -> (Blob, usize) {
+    match data.window() {
+        Some((parent, start)) => (parent.clone(), start + offset),
+        None => (data, offset),
+    }
+}
+
 impl Carve {
     pub fn new(data: Blob, offset: usize, len: usize) -> Result<Carve> {
-        if offset + len > data.as_ref().as_ref().len() {
+        if offset.checked_add(len).is_none_or(|end| end > data.len()) {
             Err(anyhow!("carve out of bounds"))
         } else {
+            let (data, offset) = flatten(data, offset);
             Ok(Carve { data, offset, len })
         }
     }
 }
 
-const EMPTY_CARVE: &[u8] = &[];
+impl BlobSource for Carve {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len.saturating_sub(offset));
+        self.data.read_at(self.offset + offset, &mut buf[..count])
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        self.data.as_slice()?.get(self.offset..(self.offset+self.len))
+    }
 
-impl AsRef<[u8]> for Carve {
-    fn as_ref(&self) -> &[u8] {
-        let data = self.data.as_ref().as_ref();
-        data.get(self.offset..(self.offset+self.len)).unwrap_or(EMPTY_CARVE)
+    fn window(&self) -> Option<(&Blob, usize)> {
+        Some((&self.data, self.offset))
     }
-}
\ No newline at end of file
+}
+
+/// Several ranges of a blob presented as one logical blob, for data stored in fragments such as
+/// file system extents. Reads are served from the fragments without joining them.
+pub struct Concat {
+    data: Blob,
+    /// Each fragment's offset in the blob and its start in the concatenation
+    fragments: Vec<(usize, usize, usize)>,
+    len: usize,
+}
+
+impl Concat {
+    pub fn new(data: Blob, ranges: &[(usize, usize)]) -> Result<Concat> {
+        let data_len = data.len();
+        let mut fragments = Vec::with_capacity(ranges.len());
+        let mut len = 0usize;
+        for (offset, length) in ranges {
+            let end = offset.checked_add(*length).ok_or_else(|| anyhow!("concat range out of bounds"))?;
+            if end > data_len {
+                return Err(anyhow!("concat range out of bounds"));
+            }
+            fragments.push((*offset, *length, len));
+            len = len.checked_add(*length).ok_or_else(|| anyhow!("concat too large"))?;
+        }
+        let (data, base) = flatten(data, 0);
+        for (offset, _, _) in &mut fragments {
+            *offset += base;
+        }
+        Ok(Concat { data, fragments, len })
+    }
+}
+
+impl BlobSource for Concat {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Copies across fragment boundaries as needed.
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        // The fragment containing the offset is the last one starting at or before it
+        let first = self.fragments.partition_point(|(_, _, start)| *start <= offset).saturating_sub(1);
+        let mut copied = 0;
+        for (fragment_offset, fragment_len, start) in &self.fragments[first..] {
+            if copied == buf.len() {
+                break;
+            }
+            let position = offset + copied;
+            if position >= start + fragment_len {
+                continue;
+            }
+            let skip = position - start;
+            let count = (fragment_len - skip).min(buf.len() - copied);
+            copied += self.data.read_at(fragment_offset + skip, &mut buf[copied..copied + count]);
+        }
+        copied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+
+    /// Checks every read of `blob` against `expected`, including reads that run past the end.
+    fn assert_reads(blob: &dyn BlobSource, expected: &[u8]) {
+        assert_eq!(blob.len(), expected.len());
+        for offset in 0..=expected.len() + 1 {
+            for length in 0..=expected.len() + 1 {
+                let mut buf = vec![0u8; length];
+                let count = blob.read_at(offset, &mut buf);
+                let want = expected.get(offset..).unwrap_or_default();
+                let want = &want[..want.len().min(length)];
+                assert_eq!(&buf[..count], want, "read of {} bytes at {}", length, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn concat_reads_across_fragments() {
+        let data: Blob = Arc::new(b"0123456789".to_vec());
+        let concat = Concat::new(data, &[(2, 3), (0, 2), (8, 2)]).unwrap();
+        assert_reads(&concat, b"2340189");
+    }
+
+    #[test]
+    fn concat_skips_zero_length_fragments() {
+        let data: Blob = Arc::new(b"0123456789".to_vec());
+        let concat = Concat::new(data, &[(0, 0), (3, 2), (0, 0), (5, 0), (7, 1), (9, 0)]).unwrap();
+        assert_reads(&concat, b"347");
+        let empty = Concat::new(Arc::new(b"0123".to_vec()), &[(1, 0), (2, 0)]).unwrap();
+        assert_reads(&empty, b"");
+    }
+
+    #[test]
+    fn concat_of_carve_reads_root() {
+        let data: Blob = Arc::new(b"0123456789".to_vec());
+        let carve: Blob = Arc::new(Carve::new(data, 4, 6).unwrap());
+        let concat = Concat::new(carve, &[(4, 2), (0, 3)]).unwrap();
+        assert_reads(&concat, b"89456");
+    }
+
+    #[test]
+    fn concat_rejects_out_of_bounds() {
+        let data: Blob = Arc::new(b"0123".to_vec());
+        assert!(Concat::new(data.clone(), &[(2, 3)]).is_err());
+        assert!(Concat::new(data, &[(usize::MAX, 2)]).is_err());
+    }
+
+    #[test]
+    fn carve_rejects_out_of_bounds() {
+        let data: Blob = Arc::new(b"0123".to_vec());
+        assert!(Carve::new(data.clone(), 2, 3).is_err());
+        assert!(Carve::new(data.clone(), 1, usize::MAX).is_err());
+        assert_reads(&Carve::new(data, 1, 3).unwrap(), b"123");
+    }
+}
@@ -0,0 +1,586 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread::available_parallelism;
+use anyhow::{Result, anyhow};
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::hash::HashAlgorithm;
+use crate::load::module_paths;
+use crate::routing::Route;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML configuration file; flags given on the command line override its values
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    pub modules: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    pub input: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    pub fuel: Option<u64>,
+
+    #[arg(long, global = true)]
+    pub memory: Option<ByteSize>,
+
+    #[arg(long, global = true)]
+    pub table: Option<usize>,
+
+    #[arg(long, global = true)]
+    pub mapped: Option<ByteSize>,
+
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Disable the live progress view (it is always disabled when stdout is not a terminal)
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Configuration parameter for a module, overriding the config file and the module's manifest
+    #[arg(long = "module-arg", value_name = "MODULE.KEY=VALUE", global = true)]
+    pub module_args: Vec<ModuleArg>,
+
+    /// Directory of YARA rules to scan every blob with
+    #[arg(long, global = true)]
+    pub yara: Option<PathBuf>,
+
+    /// Deepest blob YARA rules may carve from
+    #[arg(long, global = true)]
+    pub yara_carve_depth: Option<u32>,
+
+    /// Most blobs YARA rules may carve from any one blob
+    #[arg(long, global = true)]
+    pub yara_carves: Option<u64>,
+
+    /// JSON lines file every emitted row is appended to
+    #[arg(long, global = true)]
+    pub results: Option<PathBuf>,
+
+    /// Hash list (plain text or NSRL CSV) of known files to skip
+    #[arg(long = "allow-list", global = true)]
+    pub allow_lists: Vec<PathBuf>,
+
+    /// Hash list (plain text or NSRL CSV) of known files to flag
+    #[arg(long = "deny-list", global = true)]
+    pub deny_lists: Vec<PathBuf>,
+
+    /// Largest output a module may decompress in one call
+    #[arg(long, global = true)]
+    pub decompress_limit: Option<ByteSize>,
+
+    /// Size past which an output buffer is moved from memory to a temporary file
+    #[arg(long, global = true)]
+    pub output_spill: Option<ByteSize>,
+
+    /// Longest chain of modules invoking one another through `wadup_invoke`
+    #[arg(long, global = true)]
+    pub invoke_depth: Option<usize>,
+
+    /// Most bytes a job may hold across its output buffers
+    #[arg(long, global = true)]
+    pub quota_output_bytes: Option<ByteSize>,
+
+    /// Most output buffers a job may create
+    #[arg(long, global = true)]
+    pub quota_output_buffers: Option<usize>,
+
+    /// Most rows a job may emit in each schema
+    #[arg(long, global = true)]
+    pub quota_rows: Option<u64>,
+
+    /// Most blobs a job may carve, submit or decompress for dispatch
+    #[arg(long, global = true)]
+    pub quota_carves: Option<u64>,
+
+    /// Block size of the entropy recorded for every blob in the `wadup_entropy` schema
+    #[arg(long, value_name = "BLOCK_SIZE", global = true)]
+    pub entropy: Option<ByteSize>,
+
+    /// Hash algorithms to record for every blob in the `wadup_hashes` schema (md5, sha1, sha256,
+    /// ssdeep, tlsh)
+    #[arg(long = "hash", value_delimiter = ',', global = true)]
+    pub hashes: Vec<HashAlgorithm>,
+
+    /// Show which modules each input would be routed to without running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Cluster the blobs in the results file by the similarity of their fuzzy hashes
+    Similar {
+        /// Fuzzy hash recorded in the `wadup_hashes` rows to compare (ssdeep, tlsh)
+        #[arg(long, default_value = "ssdeep")]
+        algorithm: HashAlgorithm,
+        /// Lowest ssdeep similarity (0-100, default 50) or highest TLSH distance (default 100)
+        /// for two blobs to be clustered together
+        #[arg(long)]
+        threshold: Option<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective configuration as TOML
+    Print,
+}
+
+/// A `--module-arg`, kept as written until the module names are known: both the module name and
+/// the key may contain dots, so the split between them depends on which modules are loaded.
+#[derive(Clone, Debug)]
+pub struct ModuleArg {
+    pub name: String,
+    pub value: String,
+}
+
+impl FromStr for ModuleArg {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ModuleArg> {
+        let (name, value) = s.split_once('=').ok_or_else(|| anyhow!("module argument {:?} is not MODULE.KEY=VALUE", s))?;
+        if !name.contains('.') {
+            return Err(anyhow!("module argument {:?} is not MODULE.KEY=VALUE", s));
+        }
+        Ok(ModuleArg { name: name.to_owned(), value: value.to_owned() })
+    }
+}
+
+impl ModuleArg {
+    /// Splits the argument into the module it is for and its key. The module may be written with
+    /// or without its `.wasm` extension; the longest module name that matches is used.
+    pub fn resolve(&self, module_names: &[String]) -> Result<(String, String)> {
+        module_names.iter()
+            .flat_map(|module| {
+                let prefixes = [Some(module.as_str()), module.strip_suffix(".wasm")];
+                prefixes.into_iter().flatten().map(move |prefix| (module, prefix))
+            })
+            .filter_map(|(module, prefix)| {
+                let key = self.name.strip_prefix(prefix)?.strip_prefix('.')?;
+                (!prefix.is_empty() && !key.is_empty()).then_some((prefix.len(), module, key))
+            })
+            .max_by_key(|(length, _, _)| *length)
+            .map(|(_, module, key)| (module.clone(), key.to_owned()))
+            .ok_or_else(|| anyhow!("arguments given for unknown module in {:?}", self.name))
+    }
+}
+
+/// A size in bytes, written either as a plain integer or with a unit such as `512MiB` or `1.5GB`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ByteSize> {
+        let s = s.trim();
+        let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1000,
+            "m" | "mb" => 1000u64.pow(2),
+            "g" | "gb" => 1000u64.pow(3),
+            "t" | "tb" => 1000u64.pow(4),
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            unit => return Err(anyhow!("unknown size unit {:?} in {:?}", unit, s)),
+        };
+        if let Ok(number) = number.parse::<u64>() {
+            number.checked_mul(multiplier).map(ByteSize).ok_or_else(|| anyhow!("size {:?} is too large", s))
+        } else {
+            let number = number.parse::<f64>().map_err(|_| anyhow!("invalid size {:?}", s))?;
+            let value = number * multiplier as f64;
+            if value.is_finite() && value >= 0.0 && value < u64::MAX as f64 {
+                Ok(ByteSize(value as u64))
+            } else {
+                Err(anyhow!("size {:?} is out of range", s))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [(&str, u64); 4] = [("TiB", 1 << 40), ("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10)];
+        for (unit, size) in UNITS {
+            if self.0 >= size && self.0.is_multiple_of(size) {
+                return write!(f, "{}{}", self.0 / size, unit);
+            }
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let text = self.to_string();
+        if text == self.0.to_string() {
+            serializer.serialize_u64(self.0)
+        } else {
+            serializer.serialize_str(&text)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ByteSize, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            String(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Integer(value) => Ok(ByteSize(value)),
+            Raw::String(value) => value.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// A byte string written in hex, e.g. `"89504e47"`; whitespace between bytes is ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl FromStr for HexBytes {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<HexBytes> {
+        let digits = s.chars().filter(|c| !c.is_whitespace()).collect::<Vec<_>>();
+        if digits.len() % 2 != 0 {
+            return Err(anyhow!("hex string {:?} has an odd number of digits", s));
+        }
+        digits.chunks(2)
+            .map(|pair| {
+                let high = pair[0].to_digit(16).ok_or_else(|| anyhow!("invalid hex digit {:?} in {:?}", pair[0], s))?;
+                let low = pair[1].to_digit(16).ok_or_else(|| anyhow!("invalid hex digit {:?} in {:?}", pair[1], s))?;
+                Ok((high * 16 + low) as u8)
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(HexBytes)
+    }
+}
+
+impl fmt::Display for HexBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for HexBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<HexBytes, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Limits on what a single job may produce, so a faulty module fails its own job rather than
+/// exhausting the host.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quotas {
+    /// Total size of the job's output buffers
+    pub output_bytes: ByteSize,
+    pub output_buffers: usize,
+    /// Rows emitted in any one schema
+    pub rows: u64,
+    /// Blobs carved, submitted or decompressed for dispatch
+    pub carves: u64,
+}
+
+impl Default for Quotas {
+    fn default() -> Quotas {
+        Quotas {
+            output_bytes: ByteSize(1 << 30),
+            output_buffers: 10_000,
+            rows: 1_000_000,
+            carves: 100_000,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub modules: PathBuf,
+    pub input: PathBuf,
+    pub fuel: u64,
+    pub memory: ByteSize,
+    pub table: usize,
+    pub mapped: ByteSize,
+    pub threads: usize,
+    pub progress: bool,
+    /// Routing rules by module name, replacing the rules from the module's manifest
+    pub routes: BTreeMap<String, Vec<Route>>,
+    /// Configuration parameters by module name, layered over the module's manifest
+    pub module_args: BTreeMap<String, BTreeMap<String, toml::Value>>,
+    /// Hash algorithms recorded for every root and derived blob
+    pub hashes: Vec<HashAlgorithm>,
+    /// Block size of the entropy row recorded for every root and derived blob; no row when unset
+    pub entropy: Option<ByteSize>,
+    /// Largest output a module may decompress in one call
+    pub decompress_limit: ByteSize,
+    /// Size past which an output buffer is moved from memory to a temporary file
+    pub output_spill: ByteSize,
+    /// Longest chain of modules invoking one another through `wadup_invoke`
+    pub invoke_depth: usize,
+    /// Per-job limits on output buffers, rows and carves
+    pub quotas: Quotas,
+    /// Directory of YARA rules (`.yar`, `.yara`) every root and derived blob is scanned with
+    pub yara: Option<PathBuf>,
+    /// Deepest blob YARA rules may carve from; matches in deeper blobs are recorded but not carved
+    pub yara_carve_depth: u32,
+    /// Most blobs YARA rules may carve from any one blob
+    pub yara_carves: u64,
+    /// JSON lines file every emitted row is appended to, as read by `wadup similar`
+    pub results: Option<PathBuf>,
+    /// Hash lists of known files; listed blobs are recorded in `wadup_known` and not dispatched
+    pub allow_lists: Vec<PathBuf>,
+    /// Hash lists of known bad files; listed blobs are recorded in `wadup_known` and reported
+    pub deny_lists: Vec<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            modules: PathBuf::from("modules"),
+            input: PathBuf::from("input"),
+            fuel: 100_000_000,
+            memory: ByteSize(64 << 20),
+            table: 10_000,
+            mapped: ByteSize(1 << 30),
+            threads: available_parallelism().map(|n| n.get()).unwrap_or(1),
+            progress: true,
+            routes: BTreeMap::new(),
+            module_args: BTreeMap::new(),
+            hashes: Vec::new(),
+            entropy: None,
+            decompress_limit: ByteSize(256 << 20),
+            output_spill: ByteSize(16 << 20),
+            invoke_depth: 8,
+            quotas: Quotas::default(),
+            yara: None,
+            yara_carve_depth: 8,
+            yara_carves: 100,
+            results: None,
+            allow_lists: Vec::new(),
+            deny_lists: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Builds the effective configuration: defaults, then the `--config` file, then command line flags.
+    pub fn load(cli: &Cli) -> Result<Config> {
+        let mut config = match &cli.config {
+            Some(config_path) => Config::from_file(config_path)?,
+            None => Config::default(),
+        };
+
+        if let Some(modules) = &cli.modules { config.modules = modules.clone(); }
+        if let Some(input) = &cli.input { config.input = input.clone(); }
+        if let Some(fuel) = cli.fuel { config.fuel = fuel; }
+        if let Some(memory) = cli.memory { config.memory = memory; }
+        if let Some(table) = cli.table { config.table = table; }
+        if let Some(mapped) = cli.mapped { config.mapped = mapped; }
+        if let Some(threads) = cli.threads { config.threads = threads; }
+        if cli.no_progress { config.progress = false; }
+        if let Some(yara) = &cli.yara { config.yara = Some(yara.clone()); }
+        if let Some(yara_carve_depth) = cli.yara_carve_depth { config.yara_carve_depth = yara_carve_depth; }
+        if let Some(yara_carves) = cli.yara_carves { config.yara_carves = yara_carves; }
+        if let Some(results) = &cli.results { config.results = Some(results.clone()); }
+        if !cli.allow_lists.is_empty() { config.allow_lists = cli.allow_lists.clone(); }
+        if !cli.deny_lists.is_empty() { config.deny_lists = cli.deny_lists.clone(); }
+        if let Some(decompress_limit) = cli.decompress_limit { config.decompress_limit = decompress_limit; }
+        if let Some(output_spill) = cli.output_spill { config.output_spill = output_spill; }
+        if let Some(invoke_depth) = cli.invoke_depth { config.invoke_depth = invoke_depth; }
+        if let Some(output_bytes) = cli.quota_output_bytes { config.quotas.output_bytes = output_bytes; }
+        if let Some(output_buffers) = cli.quota_output_buffers { config.quotas.output_buffers = output_buffers; }
+        if let Some(rows) = cli.quota_rows { config.quotas.rows = rows; }
+        if let Some(carves) = cli.quota_carves { config.quotas.carves = carves; }
+        if !cli.hashes.is_empty() { config.hashes = cli.hashes.clone(); }
+        if let Some(entropy) = cli.entropy { config.entropy = Some(entropy); }
+        if !cli.module_args.is_empty() {
+            let module_names = module_paths(&config.modules)?.iter()
+                .filter_map(|p| p.file_name()?.to_str().map(str::to_owned))
+                .collect::<Vec<_>>();
+            for arg in &cli.module_args {
+                let (module, key) = arg.resolve(&module_names)?;
+                config.module_args.entry(module).or_default().insert(key, toml::Value::String(arg.value.clone()));
+            }
+        }
+
+        if config.threads == 0 {
+            return Err(anyhow!("threads must be at least 1"));
+        }
+
+        Ok(config)
+    }
+
+    fn from_file(config_path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(config_path).map_err(|e| anyhow!("unable to read config {:?}: {}", config_path, e))?;
+        let mut config: Config = toml::from_str(&text).map_err(|e| anyhow!("unable to parse config {:?}: {}", config_path, e))?;
+
+        // Relative paths in a config file are relative to the file, not the working directory
+        let base = config_path.parent().unwrap_or(Path::new(""));
+        config.modules = base.join(&config.modules);
+        config.input = base.join(&config.input);
+        config.yara = config.yara.map(|yara| base.join(yara));
+        config.results = config.results.map(|results| base.join(results));
+        config.allow_lists = config.allow_lists.iter().map(|list| base.join(list)).collect();
+        config.deny_lists = config.deny_lists.iter().map(|list| base.join(list)).collect();
+
+        Ok(config)
+    }
+
+    pub fn memory_limit(&self) -> Result<usize> {
+        usize::try_from(self.memory.0).map_err(|_| anyhow!("memory limit {} does not fit in usize", self.memory))
+    }
+
+    /// The spill threshold, saturating where it does not fit in usize.
+    pub fn output_spill(&self) -> usize {
+        usize::try_from(self.output_spill.0).unwrap_or(usize::MAX)
+    }
+
+    /// A module's parameters as the strings passed to it, layered over the manifest defaults.
+    pub fn module_config(&self, module_name: &str, defaults: &BTreeMap<String, toml::Value>) -> BTreeMap<String, String> {
+        let overrides = self.module_args.get(module_name).into_iter().flatten();
+        defaults.iter().chain(overrides)
+            .map(|(key, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_module_arg_by_file_name() {
+        let arg: ModuleArg = "module1.wasm.key=v".parse().unwrap();
+        assert_eq!(arg.resolve(&names(&["module1.wasm", "module2.wasm"])).unwrap(), ("module1.wasm".to_owned(), "key".to_owned()));
+    }
+
+    #[test]
+    fn resolves_module_arg_without_extension() {
+        let arg: ModuleArg = "module1.key=v".parse().unwrap();
+        assert_eq!(arg.resolve(&names(&["module1.wasm", "module2.wasm"])).unwrap(), ("module1.wasm".to_owned(), "key".to_owned()));
+    }
+
+    #[test]
+    fn resolves_module_arg_to_longest_name() {
+        let arg: ModuleArg = "a.b.wasm.c.d=v".parse().unwrap();
+        assert_eq!(arg.resolve(&names(&["a.wasm", "a.b.wasm"])).unwrap(), ("a.b.wasm".to_owned(), "c.d".to_owned()));
+        let arg: ModuleArg = "a.b.c=v".parse().unwrap();
+        assert_eq!(arg.resolve(&names(&["a.wasm", "a.b.wasm"])).unwrap(), ("a.b.wasm".to_owned(), "c".to_owned()));
+    }
+
+    #[test]
+    fn rejects_module_arg_for_unknown_module() {
+        let arg: ModuleArg = "module3.key=v".parse().unwrap();
+        assert!(arg.resolve(&names(&["module1.wasm"])).is_err());
+        assert!("module1=v".parse::<ModuleArg>().is_err());
+        assert!("module1.key".parse::<ModuleArg>().is_err());
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!("1024".parse::<ByteSize>().unwrap(), ByteSize(1024));
+        assert_eq!("512MiB".parse::<ByteSize>().unwrap(), ByteSize(512 << 20));
+        assert_eq!("2 kb".parse::<ByteSize>().unwrap(), ByteSize(2000));
+        assert_eq!("1.5GB".parse::<ByteSize>().unwrap(), ByteSize(1_500_000_000));
+        assert_eq!("0.5KiB".parse::<ByteSize>().unwrap(), ByteSize(512));
+        assert!("12XB".parse::<ByteSize>().is_err());
+        assert!("MiB".parse::<ByteSize>().is_err());
+        assert!("1.2.3".parse::<ByteSize>().is_err());
+        assert!("20000000TiB".parse::<ByteSize>().is_err());
+    }
+
+    #[test]
+    fn displays_byte_sizes_in_largest_exact_unit() {
+        assert_eq!(ByteSize(1 << 30).to_string(), "1GiB");
+        assert_eq!(ByteSize(1536 << 10).to_string(), "1536KiB");
+        assert_eq!(ByteSize(1000).to_string(), "1000");
+        for size in [ByteSize(0), ByteSize(3 << 40), ByteSize(12345)] {
+            assert_eq!(size.to_string().parse::<ByteSize>().unwrap(), size);
+        }
+    }
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wadup.toml");
+        fs::write(&config_path, "fuel = 5\nthreads = 3\nmapped = \"2MiB\"\n[quotas]\nrows = 7\n").unwrap();
+        let cli = Cli::parse_from(["wadup", "--config", config_path.to_str().unwrap(), "--fuel", "9", "--quota-carves", "4"]);
+        let config = Config::load(&cli).unwrap();
+        assert_eq!(config.fuel, 9);
+        assert_eq!(config.threads, 3);
+        assert_eq!(config.mapped, ByteSize(2 << 20));
+        assert_eq!(config.quotas.rows, 7);
+        assert_eq!(config.quotas.carves, 4);
+        assert_eq!(config.table, Config::default().table);
+    }
+
+    #[test]
+    fn resolves_config_paths_against_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wadup.toml");
+        fs::write(&config_path, "modules = \"mods\"\ninput = \"/data\"\nallow_lists = [\"known.txt\"]\n").unwrap();
+        let cli = Cli::parse_from(["wadup", "--config", config_path.to_str().unwrap(), "--results", "out.jsonl"]);
+        let config = Config::load(&cli).unwrap();
+        assert_eq!(config.modules, dir.path().join("mods"));
+        assert_eq!(config.input, PathBuf::from("/data"));
+        assert_eq!(config.allow_lists, vec![dir.path().join("known.txt")]);
+        // Paths given on the command line stay relative to the working directory
+        assert_eq!(config.results, Some(PathBuf::from("out.jsonl")));
+    }
+
+    #[test]
+    fn rejects_unknown_config_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wadup.toml");
+        fs::write(&config_path, "fule = 5\n").unwrap();
+        let cli = Cli::parse_from(["wadup", "--config", config_path.to_str().unwrap()]);
+        assert!(Config::load(&cli).is_err());
+    }
+}
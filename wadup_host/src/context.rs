@@ -7,14 +7,24 @@ use anyhow::{Result, anyhow};
 
 use crate::types::{Blob, DataValue};
 use crate::job::Job;
+use crate::output::OutputBuffer;
+use crate::quota::Usage;
+use crate::rows::RowCursor;
+use crate::search::Search;
 
 pub struct Context {
     pub job: Job,
     pub input: Blob,
-    pub output: Arc<Mutex<Vec<Vec<u8>>>>,
+    pub output: Arc<Mutex<Vec<OutputBuffer>>>,
     pub schema: Arc<Mutex<BiMap<String,u32>>>,
     pub column: Arc<Mutex<HashMap<u32,HashMap<String,u32>>>>,
     pub metadata: Arc<Mutex<HashMap<(u32,u32),DataValue>>>,
+    pub cursors: Arc<Mutex<Vec<RowCursor>>>,
+    /// Encoded results of `wadup_invoke`, indexed by handle
+    pub invocations: Arc<Mutex<Vec<Vec<u8>>>>,
+    pub searches: Arc<Mutex<Vec<Search>>>,
+    /// Counted against the configured quotas
+    pub usage: Arc<Mutex<Usage>>,
     pub memory_limit: usize,
     pub memory_used: usize,
     pub table_limit: usize,
@@ -0,0 +1,144 @@
+use std::io::{self, Write};
+use anyhow::{Result, anyhow};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use ruzstd::decoding::StreamingDecoder;
+use ruzstd::decoding::errors::{FrameDecoderError, ReadFrameHeaderError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zlib,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// The format numbers used by `wadup_decompress`.
+    pub fn from_id(id: u32) -> Result<Compression> {
+        match id {
+            1 => Ok(Compression::Zlib),
+            2 => Ok(Compression::Gzip),
+            3 => Ok(Compression::Bzip2),
+            4 => Ok(Compression::Xz),
+            5 => Ok(Compression::Zstd),
+            id => Err(anyhow!("unknown compression format {}", id)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Zlib => "zlib",
+            Compression::Gzip => "gzip",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// Collects decompressed bytes, failing once more than `limit` would be written.
+struct CappedWriter {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for CappedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(io::Error::other(format!("decompressed size exceeds limit of {} bytes", self.limit)));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Decodes every zstd frame in turn, since a stream may hold several, and steps over skippable
+/// frames.
+fn zstd_decompress(mut data: &[u8], output: &mut CappedWriter) -> io::Result<()> {
+    loop {
+        let frame = StreamingDecoder::new(&mut data);
+        match frame {
+            Ok(mut decoder) => {
+                io::copy(&mut decoder, output)?;
+            },
+            Err(FrameDecoderError::ReadFrameHeaderError(ReadFrameHeaderError::SkipFrame { length, .. })) => {
+                data = data.get(length as usize..).ok_or_else(|| io::Error::other("skippable frame is truncated"))?;
+            },
+            Err(e) => return Err(io::Error::other(e.to_string())),
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+    }
+}
+
+/// Decompresses a whole stream, including every member of concatenated gzip, bzip2 and zstd
+/// streams, failing if the data is corrupt or would exceed `limit` bytes.
+pub fn decompress(compression: Compression, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut output = CappedWriter { data: Vec::new(), limit };
+    let result = match compression {
+        Compression::Zlib => io::copy(&mut ZlibDecoder::new(data), &mut output).map(|_| ()),
+        Compression::Gzip => io::copy(&mut MultiGzDecoder::new(data), &mut output).map(|_| ()),
+        Compression::Bzip2 => io::copy(&mut MultiBzDecoder::new(data), &mut output).map(|_| ()),
+        Compression::Xz => lzma_rs::xz_decompress(&mut io::BufReader::new(data), &mut output).map_err(|e| match e {
+            lzma_rs::error::Error::IoError(e) => e,
+            e => io::Error::other(e.to_string()),
+        }),
+        Compression::Zstd => zstd_decompress(data, &mut output),
+    };
+    result.map_err(|e| anyhow!("{} {}", compression.name(), e))?;
+    Ok(output.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bzip2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zstd(data: &[u8]) -> Vec<u8> {
+        ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest)
+    }
+
+    #[test]
+    fn decompresses_concatenated_members() {
+        for (compression, compress) in [(Compression::Gzip, gzip as fn(&[u8]) -> Vec<u8>), (Compression::Bzip2, bzip2), (Compression::Zstd, zstd)] {
+            let mut data = compress(b"first member, ");
+            data.extend(compress(b"second member"));
+            assert_eq!(decompress(compression, &data, 1 << 20).unwrap(), b"first member, second member", "{}", compression.name());
+        }
+    }
+
+    #[test]
+    fn skips_skippable_zstd_frames() {
+        let mut data = vec![0x50, 0x2a, 0x4d, 0x18, 3, 0, 0, 0, 1, 2, 3];
+        data.extend(zstd(b"after"));
+        assert_eq!(decompress(Compression::Zstd, &data, 1 << 20).unwrap(), b"after");
+    }
+
+    #[test]
+    fn rejects_corrupt_and_oversized_data() {
+        let mut data = zstd(b"frame");
+        data.extend_from_slice(b"trailing junk");
+        assert!(decompress(Compression::Zstd, &data, 1 << 20).is_err());
+        assert!(decompress(Compression::Zstd, b"", 1 << 20).is_err());
+        assert!(decompress(Compression::Gzip, &gzip(&[0; 100]), 99).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpmc::Sender;
+use anyhow::{Result, anyhow};
+use uuid::Uuid;
+
+use crate::environment::{Environment, WadupModule};
+use crate::graph::candidates;
+use crate::carve::Carve;
+use crate::entropy::entropy_row;
+use crate::filetype::{file_type_row, identify};
+use crate::hash::hashes_row;
+use crate::hashlist::{ListKind, known_row};
+use crate::job::{Inspection, Job, JobInfo, JobKind, JobOrDie, JobResult, JobTracking};
+use crate::mmap::contiguous;
+use crate::provenance::Provenance;
+use crate::routing::matches_any;
+use crate::types::Blob;
+use crate::yara::yara_row;
+
+pub fn route<'a>(environment: &'a Environment, data: &[u8], provenance: &Provenance) -> Vec<&'a Arc<WadupModule>> {
+    candidates(&environment.modules, provenance).into_iter()
+        .filter(|m| matches_any(&m.routes, data, provenance))
+        .collect()
+}
+
+pub fn find_modules<'a>(environment: &'a Environment, names: &[&str]) -> Result<Vec<&'a Arc<WadupModule>>> {
+    names.iter()
+        .map(|name| {
+            environment.modules.iter()
+                .find(|m| m.name == *name)
+                .ok_or_else(|| anyhow!("module {:?} not found", name))
+        })
+        .collect()
+}
+
+/// Name the tracker reports inspection jobs under.
+const INSPECT: &str = "wadup_inspect";
+
+/// Queues the blob for inspection, after which a job is queued for every module it is routed to,
+/// limited to the modules downstream of the module that produced it.
+pub fn dispatch(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+    blob: Blob,
+    provenance: Provenance,
+) {
+    queue_inspection(environment, job_sender, tracking_sender, blob, provenance, None);
+}
+
+/// Queues the blob for inspection, after which a job is queued for each of the given modules,
+/// bypassing routing.
+pub fn dispatch_to(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+    blob: Blob,
+    provenance: Provenance,
+    modules: &[&Arc<WadupModule>],
+) {
+    let modules = modules.iter().map(|m| (*m).clone()).collect();
+    queue_inspection(environment, job_sender, tracking_sender, blob, provenance, Some(modules));
+}
+
+fn queue_inspection(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+    blob: Blob,
+    provenance: Provenance,
+    modules: Option<Vec<Arc<WadupModule>>>,
+) {
+    let info = JobInfo {
+        id: Uuid::new_v4(),
+        module_name: INSPECT.to_owned(),
+        file_path: (provenance.depth == 0).then(|| provenance.file_path.clone()),
+    };
+    let _ = tracking_sender.send(JobTracking::JobInfo(info.clone()));
+    let _ = job_sender.send(JobOrDie::Inspect(Box::new(Inspection {
+        info,
+        job_sender: job_sender.clone(),
+        tracking_sender: tracking_sender.clone(),
+        environment: environment.clone(),
+        blob,
+        provenance,
+        modules,
+    })));
+}
+
+/// Runs the host services on a blob on a worker thread, then queues its module jobs.
+pub fn process_inspection(inspection: Box<Inspection>) -> JobResult {
+    let Inspection { info, job_sender, tracking_sender, environment, blob, mut provenance, modules } = *inspection;
+    let modules = {
+        // Hashing, identification, routing and scanning need one slice, so a blob that isn't
+        // contiguous is copied for the inspection and counted against the mapped budget until done;
+        // with no room for the copy the blob is skipped
+        let contiguous = match contiguous(blob.clone(), environment.mapped.clone(), tracking_sender.clone()) {
+            Ok(contiguous) => contiguous,
+            Err(e) => {
+                return JobResult {
+                    id: info.id,
+                    message: None,
+                    error: Some(format!("{} {:?} skipped: {}", info.module_name, info.file_path, e)),
+                    filtered: false,
+                };
+            },
+        };
+        let data = contiguous.as_slice().unwrap_or_default();
+        if !inspect(&environment, &job_sender, &tracking_sender, &blob, data, &mut provenance) {
+            return JobResult {
+                id: info.id,
+                message: Some(format!("{} {:?} allow-listed", info.module_name, info.file_path)),
+                error: None,
+                filtered: false,
+            };
+        }
+        match modules {
+            Some(modules) => modules,
+            None => route(&environment, data, &provenance).into_iter().cloned().collect(),
+        }
+    };
+    let modules = modules.iter().collect::<Vec<_>>();
+    queue(&environment, &job_sender, &tracking_sender, blob, Arc::new(provenance), &modules);
+    JobResult {
+        id: info.id,
+        message: Some(format!("{} {:?} queued for {} modules", info.module_name, info.file_path, modules.len())),
+        error: None,
+        filtered: false,
+    }
+}
+
+/// Host-side services run once on every blob before it is routed: allow and deny list lookup,
+/// file type identification, configured hashes and entropy, and YARA scanning, whose matches are
+/// recorded in the provenance and may carve new blobs. Returns false if the blob is allow-listed
+/// and should not be dispatched.
+fn inspect(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+    blob: &Blob,
+    data: &[u8],
+    provenance: &mut Provenance,
+) -> bool {
+    let mut rows = Vec::new();
+
+    if let Some(known) = environment.hash_lists.as_ref().and_then(|lists| lists.check(data)) {
+        let row = known_row(&known, provenance);
+        if let Err(e) = environment.rows.emit(row, tracking_sender) {
+            let _ = tracking_sender.send(JobTracking::Output(format!("ERROR: {}", e)));
+        }
+        match known.kind {
+            ListKind::Allow => {
+                let _ = tracking_sender.send(JobTracking::AllowListed);
+                return false;
+            },
+            ListKind::Deny => {
+                let _ = tracking_sender.send(JobTracking::DenyListed(format!(
+                    "deny-listed blob in {:?} at offset {} depth {}: {} {} from {}",
+                    provenance.file_path, provenance.offset, provenance.depth, known.algorithm.name(), known.digest, known.source,
+                )));
+            },
+        }
+    }
+
+    let file_type = identify(data);
+    provenance.file_type = Some(file_type);
+    rows.push(file_type_row(file_type, provenance));
+
+    if !environment.config.hashes.is_empty() {
+        rows.push(hashes_row(&environment.config.hashes, data, provenance));
+    }
+
+    if let Some(block_size) = environment.config.entropy {
+        rows.push(entropy_row(data, block_size.0 as usize, provenance));
+    }
+
+    if let Some(rules) = &environment.yara {
+        match rules.scan(data) {
+            Ok(matches) => provenance.yara = matches,
+            Err(e) => {
+                let _ = tracking_sender.send(JobTracking::Output(format!("ERROR: {}", e)));
+            },
+        }
+        rows.extend(provenance.yara.iter().map(|m| yara_row(m, provenance)));
+    }
+
+    for row in rows {
+        if let Err(e) = environment.rows.emit(row, tracking_sender) {
+            let _ = tracking_sender.send(JobTracking::Output(format!("ERROR: {}", e)));
+        }
+    }
+
+    // Carves are queued for inspection like any other blob, so the depth and the number carved
+    // from one blob are limited to keep overlapping rules from carving without end
+    if provenance.depth >= environment.config.yara_carve_depth {
+        return true;
+    }
+    let mut carves = 0;
+    for rule_match in &provenance.yara {
+        let Some(carve_length) = rule_match.carve else {
+            continue;
+        };
+        let rule = format!("{}.{}", rule_match.namespace, rule_match.rule);
+        // A rule matching a blob it carved, or one derived from it, would carve the same data again
+        if provenance.yara_carved_by.contains(&rule) {
+            continue;
+        }
+        let mut offsets = rule_match.strings.iter().map(|s| s.offset).collect::<Vec<_>>();
+        offsets.sort_unstable();
+        offsets.dedup();
+        for offset in offsets {
+            let remaining = data.len() as u64 - offset;
+            let length = carve_length.unwrap_or(remaining).min(remaining);
+            // A carve of the whole blob would match again forever
+            if offset == 0 && length == data.len() as u64 {
+                continue;
+            }
+            if carves == environment.config.yara_carves {
+                let _ = tracking_sender.send(JobTracking::Output(format!(
+                    "ERROR: YARA carves from {:?} at offset {} depth {} limited to {}",
+                    provenance.file_path, provenance.offset, provenance.depth, carves,
+                )));
+                return true;
+            }
+            let Ok(carve) = Carve::new(blob.clone(), offset as usize, length as usize) else {
+                continue;
+            };
+            carves += 1;
+            let mut child = provenance.host_child(offset);
+            child.hint = Some(rule_match.rule.clone());
+            child.yara_carved_by.push(rule.clone());
+            dispatch(environment, job_sender, tracking_sender, Arc::new(carve), child);
+        }
+    }
+    true
+}
+
+/// Queues a job for each of the given modules that exports `wadup_run`, registering each with the
+/// tracker first.
+fn queue(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+    blob: Blob,
+    provenance: Arc<Provenance>,
+    modules: &[&Arc<WadupModule>],
+) {
+    for module in modules.iter().filter(|m| m.run) {
+        let info = JobInfo {
+            id: Uuid::new_v4(),
+            module_name: module.name.clone(),
+            file_path: (provenance.depth == 0).then(|| provenance.file_path.clone()),
+        };
+        let _ = tracking_sender.send(JobTracking::JobInfo(info.clone()));
+        let _ = job_sender.send(JobOrDie::Job(Job {
+            info,
+            job_sender: job_sender.clone(),
+            tracking_sender: tracking_sender.clone(),
+            environment: environment.clone(),
+            module: (*module).clone(),
+            blob: blob.clone(),
+            provenance: provenance.clone(),
+            kind: JobKind::Run,
+            invoked_by: Vec::new(),
+        }));
+    }
+}
+
+/// Queues the reduce job of every module that exports `wadup_reduce`, returning the job ids.
+pub fn dispatch_reduce(
+    environment: &Arc<Environment>,
+    job_sender: &Sender<JobOrDie>,
+    tracking_sender: &Sender<JobTracking>,
+) -> Vec<Uuid> {
+    let mut ids = Vec::new();
+    let blob: Blob = Arc::new(Vec::<u8>::new());
+    let provenance = Arc::new(Provenance::root(Path::new("")));
+    for module in environment.modules.iter().filter(|m| m.reduce) {
+        let info = JobInfo {
+            id: Uuid::new_v4(),
+            module_name: module.name.clone(),
+            file_path: None,
+        };
+        ids.push(info.id);
+        let _ = tracking_sender.send(JobTracking::JobInfo(info.clone()));
+        let _ = job_sender.send(JobOrDie::Job(Job {
+            info,
+            job_sender: job_sender.clone(),
+            tracking_sender: tracking_sender.clone(),
+            environment: environment.clone(),
+            module: module.clone(),
+            blob: blob.clone(),
+            provenance: provenance.clone(),
+            kind: JobKind::Reduce,
+            invoked_by: Vec::new(),
+        }));
+    }
+    ids
+}
@@ -0,0 +1,31 @@
+use anyhow::{Result, anyhow};
+
+/// Key/value pairs handed to modules: each key and each value is a little endian u32 length
+/// followed by that many UTF8 bytes. Keys may repeat.
+pub fn encode_pairs<K: AsRef<str>, V: AsRef<str>>(pairs: impl IntoIterator<Item = (K, V)>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for (key, value) in pairs {
+        encode_str(&mut encoded, key.as_ref());
+        encode_str(&mut encoded, value.as_ref());
+    }
+    encoded
+}
+
+fn encode_str(encoded: &mut Vec<u8>, value: &str) {
+    encoded.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    encoded.extend_from_slice(value.as_bytes());
+}
+
+/// Decodes a list of byte strings from modules, each a little endian u32 length followed by that
+/// many bytes.
+pub fn decode_list(mut encoded: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut list = Vec::new();
+    while !encoded.is_empty() {
+        let (len, rest) = encoded.split_first_chunk::<4>().ok_or_else(|| anyhow!("truncated list length"))?;
+        let len = u32::from_le_bytes(*len) as usize;
+        let item = rest.get(..len).ok_or_else(|| anyhow!("truncated list item"))?;
+        list.push(item.to_vec());
+        encoded = &rest[len..];
+    }
+    Ok(list)
+}
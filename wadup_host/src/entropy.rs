@@ -0,0 +1,82 @@
+use crate::provenance::Provenance;
+use crate::rows::Row;
+use crate::types::{BlobSource, DataValue};
+
+/// Schema of the rows emitted for every blob when `entropy` is configured.
+pub const ENTROPY_SCHEMA: &str = "wadup_entropy";
+
+pub type Histogram = [u64; 256];
+
+/// How much of a source is read at a time when it isn't held in one slice.
+pub const CHUNK_SIZE: usize = 1 << 16;
+
+pub fn histogram(data: &[u8]) -> Histogram {
+    let mut histogram = [0u64; 256];
+    for byte in data {
+        histogram[*byte as usize] += 1;
+    }
+    histogram
+}
+
+/// The histogram of bytes `start..end` of a source, read a chunk at a time.
+pub fn histogram_at(data: &(impl BlobSource + ?Sized), start: usize, end: usize) -> Histogram {
+    let mut histogram = [0u64; 256];
+    let mut chunk = vec![0u8; CHUNK_SIZE.min(end.saturating_sub(start))];
+    let mut position = start;
+    while position < end {
+        let count = data.read_at(position, &mut chunk[..(end - position).min(CHUNK_SIZE)]);
+        if count == 0 {
+            break;
+        }
+        for byte in &chunk[..count] {
+            histogram[*byte as usize] += 1;
+        }
+        position += count;
+    }
+    histogram
+}
+
+/// Shannon entropy in bits per byte, from 0 for a single repeated byte to 8 for uniform data.
+pub fn entropy(histogram: &Histogram) -> f64 {
+    let total = histogram.iter().sum::<u64>() as f64;
+    if total == 0.0 {
+        return 0.0;
+    }
+    -histogram.iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total;
+            p * p.log2()
+        })
+        .sum::<f64>()
+}
+
+/// Splits data into blocks of `block_size` bytes, the last possibly shorter. A block size of 0
+/// treats the data as a single block.
+pub fn blocks(data: &[u8], block_size: usize) -> impl Iterator<Item = &[u8]> {
+    let block_size = if block_size == 0 { data.len().max(1) } else { block_size };
+    data.chunks(block_size)
+}
+
+/// The entropy row for a blob: where it came from, the entropy of the whole blob and the range of
+/// entropies of its blocks.
+pub fn entropy_row(data: &[u8], block_size: usize, provenance: &Provenance) -> Row {
+    let (min, max) = blocks(data, block_size)
+        .map(|block| entropy(&histogram(block)))
+        .fold(None, |range: Option<(f64, f64)>, e| Some(range.map_or((e, e), |(min, max)| (min.min(e), max.max(e)))))
+        .unwrap_or_default();
+    Row {
+        schema: ENTROPY_SCHEMA.to_owned(),
+        values: vec![
+            ("file_path".to_owned(), DataValue::StringValue(provenance.file_path.to_string_lossy().into_owned())),
+            ("offset".to_owned(), DataValue::Int64Value(provenance.offset as i64)),
+            ("depth".to_owned(), DataValue::Int64Value(provenance.depth as i64)),
+            ("size".to_owned(), DataValue::Int64Value(data.len() as i64)),
+            ("entropy".to_owned(), DataValue::Float64Value(entropy(&histogram(data)))),
+            ("block_size".to_owned(), DataValue::Int64Value(block_size as i64)),
+            ("min_block_entropy".to_owned(), DataValue::Float64Value(min)),
+            ("max_block_entropy".to_owned(), DataValue::Float64Value(max)),
+        ],
+        tags: provenance.tags.snapshot(),
+    }
+}
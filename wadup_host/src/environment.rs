@@ -1,70 +1,90 @@
-use clap::Parser;
-use wasmtime::{Config, Engine, Linker, Module};
-use std::{fs, path::PathBuf, sync::Arc};
-use anyhow::Result;
-use crate::{bindings::add_to_linker, context::Context, load::load_module};
-
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-pub struct Cli {
-    #[arg(long)]
-    pub modules: PathBuf,
-
-    #[arg(long)]
-    pub input: PathBuf,
-
-    #[arg(long)]
-    pub fuel: u64,
-
-    #[arg(long)]
-    pub memory: usize,
-
-    #[arg(long)]
-    pub table: usize,
-
-    #[arg(long)]
-    pub mapped: u64,
-
-    #[arg(long)]
-    pub threads: usize,
+use wasmtime::{Engine, Linker, Module};
+use std::{collections::BTreeMap, sync::Arc};
+use anyhow::{Result, anyhow};
+use crate::{bindings::add_to_linker, config::Config, context::Context, graph::{self, Consumes}, hashlist::HashLists, job::JobKind, load::{load_module, load_signatures, module_paths}, manifest::load_manifest, mmap::MappedBudget, routing::Route, rows::Rows, signature::Signature, yara::{Rules, load_rules}};
+
+pub struct WadupModule {
+    pub name: String,
+    pub module: Arc<Module>,
+    pub routes: Vec<Route>,
+    pub signatures: Vec<Signature>,
+    pub config: BTreeMap<String, String>,
+    /// Whether the module exports `wadup_run`
+    pub run: bool,
+    /// Whether the module exports `wadup_reduce`
+    pub reduce: bool,
+    pub consumes: Consumes,
+    pub produces: Vec<String>,
 }
 
 pub struct Environment {
     pub engine: Engine,
     pub linker: Linker<Context>,
-    pub modules: Vec<(String, Arc<Module>)>,
-    pub args: Cli,
+    pub modules: Vec<Arc<WadupModule>>,
+    pub rows: Rows,
+    pub yara: Option<Rules>,
+    pub hash_lists: Option<HashLists>,
+    pub mapped: Arc<MappedBudget>,
+    pub config: Config,
+    /// Problems found while loading that don't stop the run, such as YARA rules that don't compile
+    pub warnings: Vec<String>,
 }
 
 impl Environment {
-    pub fn create() -> Result<Environment> {
-        let args = Cli::parse();
-
-        let mut config = Config::new();
-        config.consume_fuel(true);
+    pub fn create(config: Config) -> Result<Environment> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
 
-        let engine = Engine::new(&config)?;
+        let engine = Engine::new(&engine_config)?;
 
         let mut linker: Linker<Context> = Linker::new(&engine);
         add_to_linker(&mut linker)?;
 
-        let module_paths = fs::read_dir(&args.modules)?
-            .filter_map(|p| p.ok() )
-            .map(|p| p.path())
-            .filter(|p| p.extension().map(|s| s == "wasm").unwrap_or(false))
-            .collect::<Vec<_>>();
-
-        let modules = module_paths.iter()
-            .map(|p| load_module(&engine, p))
-            .collect::<Result<Vec<_>,_>>()?;
-
-        let modules = modules.into_iter().map(|(n, m)| (n, Arc::new(m))).collect::<Vec<_>>();
+        let modules = module_paths(&config.modules)?.iter()
+            .map(|p| {
+                let (name, module) = load_module(&engine, p)?;
+                let manifest = load_manifest(p)?;
+                let routes = config.routes.get(&name).cloned().unwrap_or(manifest.route);
+                let mut signatures = load_signatures(p)?;
+                signatures.extend(manifest.signature);
+                let module_config = config.module_config(&name, &manifest.config);
+                let run = module.get_export(JobKind::Run.entry_point()).is_some();
+                let reduce = module.get_export(JobKind::Reduce.entry_point()).is_some();
+                Ok(Arc::new(WadupModule { name, module: Arc::new(module), routes, signatures, config: module_config, run, reduce, consumes: manifest.consumes, produces: manifest.produces }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(name) = config.routes.keys().find(|n| !modules.iter().any(|m| &&m.name == n)) {
+            return Err(anyhow!("routes configured for unknown module {:?}", name));
+        }
+        if let Some(name) = config.module_args.keys().find(|n| !modules.iter().any(|m| &&m.name == n)) {
+            return Err(anyhow!("arguments given for unknown module {:?}", name));
+        }
+
+        graph::validate(&modules)?;
+
+        let mut warnings = Vec::new();
+        let yara = match config.yara.as_deref().map(load_rules).transpose()? {
+            Some((rules, rule_warnings)) => {
+                warnings.extend(rule_warnings);
+                Some(rules)
+            },
+            None => None,
+        };
+        let hash_lists = HashLists::load(&config.allow_lists, &config.deny_lists)?;
+        let rows = Rows::new(modules.iter().any(|m| m.reduce), config.results.as_deref())?;
+        let mapped = Arc::new(MappedBudget::new(config.mapped.0));
 
         Ok(Environment {
             engine,
             linker,
             modules,
-            args,
+            rows,
+            yara,
+            hash_lists,
+            mapped,
+            config,
+            warnings,
         })
     }
 }
\ No newline at end of file
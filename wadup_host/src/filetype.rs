@@ -0,0 +1,127 @@
+use crate::provenance::Provenance;
+use crate::rows::Row;
+use crate::types::DataValue;
+
+/// Schema of the row emitted for every blob with its identified file type.
+pub const FILE_TYPE_SCHEMA: &str = "wadup_file_type";
+
+/// A file type identified from a blob's leading bytes: a short lowercase name and its MIME type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileType {
+    pub name: &'static str,
+    pub mime: &'static str,
+}
+
+impl FileType {
+    const fn new(name: &'static str, mime: &'static str) -> FileType {
+        FileType { name, mime }
+    }
+
+    /// Whether a route entry names this type, either by its name or its MIME type.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.mime.eq_ignore_ascii_case(name)
+    }
+}
+
+const EMPTY: FileType = FileType::new("empty", "application/x-empty");
+const TEXT: FileType = FileType::new("text", "text/plain");
+const DATA: FileType = FileType::new("data", "application/octet-stream");
+
+/// Bytes expected at an offset.
+type Magic = (usize, &'static [u8]);
+
+/// Magic numbers checked in order; the first whose bytes are all present at their offsets wins.
+/// Longer and more specific magics come before the shorter ones they overlap.
+const MAGICS: &[(FileType, &[Magic])] = &[
+    (FileType::new("pe", "application/vnd.microsoft.portable-executable"), &[(0, b"MZ")]),
+    (FileType::new("elf", "application/x-elf"), &[(0, b"\x7fELF")]),
+    (FileType::new("macho", "application/x-mach-binary"), &[(0, b"\xcf\xfa\xed\xfe")]),
+    (FileType::new("macho", "application/x-mach-binary"), &[(0, b"\xce\xfa\xed\xfe")]),
+    (FileType::new("macho", "application/x-mach-binary"), &[(0, b"\xfe\xed\xfa\xcf")]),
+    (FileType::new("macho", "application/x-mach-binary"), &[(0, b"\xfe\xed\xfa\xce")]),
+    (FileType::new("wasm", "application/wasm"), &[(0, b"\0asm")]),
+    (FileType::new("dex", "application/vnd.android.dex"), &[(0, b"dex\n")]),
+    (FileType::new("zip", "application/zip"), &[(0, b"PK\x03\x04")]),
+    (FileType::new("zip", "application/zip"), &[(0, b"PK\x05\x06")]),
+    (FileType::new("gzip", "application/gzip"), &[(0, b"\x1f\x8b")]),
+    (FileType::new("bzip2", "application/x-bzip2"), &[(0, b"BZh")]),
+    (FileType::new("xz", "application/x-xz"), &[(0, b"\xfd7zXZ\0")]),
+    (FileType::new("zstd", "application/zstd"), &[(0, b"\x28\xb5\x2f\xfd")]),
+    (FileType::new("7z", "application/x-7z-compressed"), &[(0, b"7z\xbc\xaf\x27\x1c")]),
+    (FileType::new("rar", "application/vnd.rar"), &[(0, b"Rar!\x1a\x07")]),
+    (FileType::new("cab", "application/vnd.ms-cab-compressed"), &[(0, b"MSCF\0\0\0\0")]),
+    (FileType::new("tar", "application/x-tar"), &[(257, b"ustar")]),
+    (FileType::new("iso9660", "application/x-iso9660-image"), &[(0x8001, b"CD001")]),
+    (FileType::new("ole", "application/x-ole-storage"), &[(0, b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1")]),
+    (FileType::new("pdf", "application/pdf"), &[(0, b"%PDF-")]),
+    (FileType::new("rtf", "text/rtf"), &[(0, b"{\\rtf")]),
+    (FileType::new("png", "image/png"), &[(0, b"\x89PNG\r\n\x1a\n")]),
+    (FileType::new("jpeg", "image/jpeg"), &[(0, b"\xff\xd8\xff")]),
+    (FileType::new("gif", "image/gif"), &[(0, b"GIF87a")]),
+    (FileType::new("gif", "image/gif"), &[(0, b"GIF89a")]),
+    (FileType::new("tiff", "image/tiff"), &[(0, b"II*\0")]),
+    (FileType::new("tiff", "image/tiff"), &[(0, b"MM\0*")]),
+    (FileType::new("webp", "image/webp"), &[(0, b"RIFF"), (8, b"WEBP")]),
+    (FileType::new("wav", "audio/wav"), &[(0, b"RIFF"), (8, b"WAVE")]),
+    (FileType::new("avi", "video/x-msvideo"), &[(0, b"RIFF"), (8, b"AVI ")]),
+    (FileType::new("mp4", "video/mp4"), &[(4, b"ftyp")]),
+    (FileType::new("mp3", "audio/mpeg"), &[(0, b"ID3")]),
+    (FileType::new("ogg", "application/ogg"), &[(0, b"OggS")]),
+    (FileType::new("flac", "audio/flac"), &[(0, b"fLaC")]),
+    (FileType::new("sqlite", "application/vnd.sqlite3"), &[(0, b"SQLite format 3\0")]),
+    (FileType::new("pcap", "application/vnd.tcpdump.pcap"), &[(0, b"\xd4\xc3\xb2\xa1")]),
+    (FileType::new("pcap", "application/vnd.tcpdump.pcap"), &[(0, b"\xa1\xb2\xc3\xd4")]),
+    (FileType::new("pcapng", "application/x-pcapng"), &[(0, b"\x0a\x0d\x0d\x0a")]),
+    (FileType::new("evtx", "application/x-ms-evtx"), &[(0, b"ElfFile\0")]),
+    (FileType::new("registry", "application/x-ms-registry"), &[(0, b"regf")]),
+    (FileType::new("lnk", "application/x-ms-shortcut"), &[(0, b"\x4c\0\0\0\x01\x14\x02\0")]),
+    (FileType::new("xml", "application/xml"), &[(0, b"<?xml")]),
+    (FileType::new("xml", "application/xml"), &[(0, b"\xef\xbb\xbf<?xml")]),
+    (FileType::new("script", "text/x-script"), &[(0, b"#!")]),
+];
+
+/// How much of a blob is checked when deciding between text and binary data.
+const TEXT_SAMPLE: usize = 8192;
+
+/// Identifies a blob from its magic number, falling back to `text` for printable UTF8 and `data`
+/// for anything else.
+pub fn identify(data: &[u8]) -> FileType {
+    if data.is_empty() {
+        return EMPTY;
+    }
+
+    let magic = MAGICS.iter().find(|(_, magics)| {
+        magics.iter().all(|(offset, bytes)| data.get(*offset..offset + bytes.len()) == Some(*bytes))
+    });
+    if let Some((file_type, _)) = magic {
+        return *file_type;
+    }
+
+    let sample = &data[..data.len().min(TEXT_SAMPLE)];
+    // A sample may end part way through a multibyte character
+    let valid = match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    let printable = sample.iter().all(|b| !b.is_ascii_control() || b.is_ascii_whitespace());
+    if valid && printable {
+        TEXT
+    } else {
+        DATA
+    }
+}
+
+/// The file type row for a blob: where it came from and its identified type.
+pub fn file_type_row(file_type: FileType, provenance: &Provenance) -> Row {
+    Row {
+        schema: FILE_TYPE_SCHEMA.to_owned(),
+        values: vec![
+            ("file_path".to_owned(), DataValue::StringValue(provenance.file_path.to_string_lossy().into_owned())),
+            ("offset".to_owned(), DataValue::Int64Value(provenance.offset as i64)),
+            ("depth".to_owned(), DataValue::Int64Value(provenance.depth as i64)),
+            ("type".to_owned(), DataValue::StringValue(file_type.name.to_owned())),
+            ("mime".to_owned(), DataValue::StringValue(file_type.mime.to_owned())),
+        ],
+        tags: provenance.tags.snapshot(),
+    }
+}
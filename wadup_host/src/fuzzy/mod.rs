@@ -0,0 +1,37 @@
+// Similarity digests. Unlike cryptographic hashes these change only a little when the data
+// changes a little, so related samples can be found by comparing digests.
+mod ssdeep;
+mod tlsh;
+
+pub use ssdeep::{ssdeep, ssdeep_similarity};
+pub use tlsh::{TLSH_NULL, tlsh, tlsh_distance};
+
+/// Deterministic pseudo-random bytes (xorshift64) for the known-answer tests.
+#[cfg(test)]
+fn sample(len: usize, seed: u64) -> Vec<u8> {
+    let mut x = seed;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            (x >> 56) as u8
+        })
+        .collect()
+}
+
+/// Deterministic text of words chosen by `sample`, for the known-answer tests.
+#[cfg(test)]
+fn words(len: usize, seed: u64) -> Vec<u8> {
+    const WORDS: [&[u8]; 10] = [b"alpha", b"beta", b"gamma", b"delta", b"epsilon", b"zeta", b"eta", b"theta", b"iota", b"kappa"];
+    let choices = sample(len, seed);
+    let mut text = Vec::new();
+    let mut i = 0;
+    while text.len() < len {
+        text.extend_from_slice(WORDS[choices[i % len] as usize % 10]);
+        text.push(if choices[(i + 1) % len].is_multiple_of(7) { b'\n' } else { b' ' });
+        i += 1;
+    }
+    text.truncate(len);
+    text
+}
@@ -0,0 +1,226 @@
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCK_SIZE: u32 = 3;
+const SPAMSUM_LENGTH: usize = 64;
+const HASH_PRIME: u32 = 0x01000193;
+const HASH_INIT: u32 = 0x28021967;
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Default)]
+struct RollingHash {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl RollingHash {
+    fn roll(&mut self, c: u8) -> u32 {
+        let c = c as u32;
+        self.h2 = self.h2.wrapping_sub(self.h1).wrapping_add(ROLLING_WINDOW as u32 * c);
+        self.h1 = self.h1.wrapping_add(c).wrapping_sub(self.window[self.n % ROLLING_WINDOW] as u32);
+        self.window[self.n % ROLLING_WINDOW] = c as u8;
+        self.n += 1;
+        self.h3 = (self.h3 << 5) ^ c;
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+fn sum_hash(c: u8, h: u32) -> u32 {
+    h.wrapping_mul(HASH_PRIME) ^ c as u32
+}
+
+/// The ssdeep style digest of some data, `block_size:digest:double_block_size_digest`. The data is
+/// split wherever a rolling hash of the last few bytes hits a trigger value, and each piece
+/// contributes one base64 character, so similar data shares long runs of the digest.
+pub fn ssdeep(data: &[u8]) -> String {
+    let mut block_size = MIN_BLOCK_SIZE;
+    while (block_size as usize) * SPAMSUM_LENGTH < data.len() {
+        block_size *= 2;
+    }
+
+    loop {
+        let mut rolling = RollingHash::default();
+        let mut h1 = HASH_INIT;
+        let mut h2 = HASH_INIT;
+        let mut digest1 = String::new();
+        let mut digest2 = String::new();
+        // The piece last hashed once a digest is full, used as its final character when the data
+        // ends exactly at a trigger point
+        let mut last1 = None;
+        let mut last2 = None;
+        let mut rolled = 0;
+        for c in data {
+            h1 = sum_hash(*c, h1);
+            h2 = sum_hash(*c, h2);
+            rolled = rolling.roll(*c);
+            if rolled % block_size == block_size - 1 {
+                if digest1.len() < SPAMSUM_LENGTH - 1 {
+                    digest1.push(B64[(h1 % 64) as usize] as char);
+                    h1 = HASH_INIT;
+                } else {
+                    last1 = Some(B64[(h1 % 64) as usize] as char);
+                }
+                if rolled % (block_size * 2) == block_size * 2 - 1 {
+                    if digest2.len() < SPAMSUM_LENGTH / 2 - 1 {
+                        digest2.push(B64[(h2 % 64) as usize] as char);
+                        h2 = HASH_INIT;
+                    } else {
+                        last2 = Some(B64[(h2 % 64) as usize] as char);
+                    }
+                }
+            }
+        }
+
+        // Too few pieces to compare meaningfully, so try again with smaller pieces
+        if block_size > MIN_BLOCK_SIZE && digest1.len() < SPAMSUM_LENGTH / 2 {
+            block_size /= 2;
+            continue;
+        }
+
+        if rolled != 0 {
+            digest1.push(B64[(h1 % 64) as usize] as char);
+            digest2.push(B64[(h2 % 64) as usize] as char);
+        } else {
+            digest1.extend(last1);
+            digest2.extend(last2);
+        }
+        return format!("{}:{}:{}", block_size, digest1, digest2);
+    }
+}
+
+/// Runs of more than three identical characters carry little information and are shortened.
+fn eliminate_sequences(digest: &str) -> Vec<u8> {
+    let mut result = Vec::with_capacity(digest.len());
+    for c in digest.bytes() {
+        if result.len() < 3 || result[result.len() - 3..].iter().any(|r| *r != c) {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn has_common_substring(a: &[u8], b: &[u8]) -> bool {
+    a.len() >= ROLLING_WINDOW && b.len() >= ROLLING_WINDOW
+        && a.windows(ROLLING_WINDOW).any(|w| b.windows(ROLLING_WINDOW).any(|v| v == w))
+}
+
+/// Edit distance where a substitution costs as much as a deletion and an insertion.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == cb { 0 } else { 2 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+fn score_strings(a: &[u8], b: &[u8], block_size: u64) -> u32 {
+    if !has_common_substring(a, b) {
+        return 0;
+    }
+    let distance = edit_distance(a, b) as u64;
+    let score = distance * SPAMSUM_LENGTH as u64 / (a.len() + b.len()) as u64;
+    let score = 100 * score / SPAMSUM_LENGTH as u64;
+    if score >= 100 {
+        return 0;
+    }
+    let score = 100 - score;
+    // Small block sizes can't produce long enough digests to justify a high score
+    let cap = block_size / MIN_BLOCK_SIZE as u64 * a.len().min(b.len()) as u64;
+    if block_size >= (99 + ROLLING_WINDOW as u64) / ROLLING_WINDOW as u64 * MIN_BLOCK_SIZE as u64 {
+        score as u32
+    } else {
+        score.min(cap) as u32
+    }
+}
+
+fn parse(digest: &str) -> Option<(u64, Vec<u8>, Vec<u8>)> {
+    let mut parts = digest.splitn(3, ':');
+    let block_size = parts.next()?.parse().ok()?;
+    let digest1 = eliminate_sequences(parts.next()?);
+    let digest2 = eliminate_sequences(parts.next()?);
+    Some((block_size, digest1, digest2))
+}
+
+/// Similarity of two digests from 0 (unrelated or incomparable) to 100.
+pub fn ssdeep_similarity(a: &str, b: &str) -> u32 {
+    let (Some((size_a, a1, a2)), Some((size_b, b1, b2))) = (parse(a), parse(b)) else {
+        return 0;
+    };
+    if size_a == size_b && a1 == b1 && a2 == b2 {
+        return 100;
+    }
+    if size_a == size_b {
+        score_strings(&a1, &b1, size_a).max(score_strings(&a2, &b2, size_a * 2))
+    } else if size_a == size_b * 2 {
+        score_strings(&a1, &b2, size_a)
+    } else if size_b == size_a * 2 {
+        score_strings(&a2, &b1, size_b)
+    } else {
+        0
+    }
+}
+
+/// Expected values from the reference `ssdeep` 2.14.1.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::{sample, words};
+
+    fn edited(mut data: Vec<u8>, at: usize, replacement: &[u8]) -> Vec<u8> {
+        data[at..at + replacement.len()].copy_from_slice(replacement);
+        data
+    }
+
+    #[test]
+    fn digests() {
+        assert_eq!(ssdeep(b""), "3::");
+        assert_eq!(ssdeep(b"abc"), "3:uG:uG");
+        assert_eq!(ssdeep(&sample(1000, 1)), "24:Nxi8t/b3VrwujGVMeR4BXLklHifNhFD3eMOfk1/Efsh7:Npt/b3pwuSSjBXLklCh35Oe0U");
+        assert_eq!(ssdeep(&sample(10000, 2)), "192:kP2XUjP/d4kt+4YMk5YSfRAV1NSUOV28pZ0fo5N2uUAoKk7NUNlBldu:kuyXdju5YiCvNu28p8715T");
+        assert_eq!(ssdeep(&sample(5200, 2)), "96:kUG2XUjP/484kt4t9MrYAL12b7xXbP6ewuHj2oRANLyOrNYJzlzcNtZ4yE28p2:kP2XUjP/d4kt+4YMk5YSfRAV1NSUOV2f");
+        assert_eq!(
+            ssdeep(&sample(100000, 3)),
+            "1536:/Ud0H5x5Td9+4/H+CLm+iFgs1seZxAnz9y5SZ2rFMXhl6qXDOjJTqwZN5nwDs:MdCr5Td9PP8+m1NAzSLRMXD6QyJRnwDs",
+        );
+        assert_eq!(
+            ssdeep(&words(50000, 4)),
+            "192:HtwM+NmNp6oYcWna1dro711Q11k61Lcgs0n+QJVNUlp76c6XHd9O599y1nCM1rHz:HYLsplufqZh5jtIDUv",
+        );
+    }
+
+    #[test]
+    fn similarities() {
+        let random = sample(100000, 3);
+        let mut flipped = random.clone();
+        for i in [10000, 50000, 90000] {
+            flipped[i] ^= 0xff;
+        }
+        assert_eq!(ssdeep_similarity(&ssdeep(&flipped), &ssdeep(&random)), 96);
+
+        let small = sample(10000, 2);
+        let replaced = edited(small.clone(), 4000, &sample(2000, 7));
+        let half = &small[..5200];
+        assert_eq!(ssdeep_similarity(&ssdeep(&replaced), &ssdeep(&small)), 79);
+        // Digests with block sizes 96 and 192
+        assert_eq!(ssdeep_similarity(&ssdeep(half), &ssdeep(&small)), 75);
+        assert_eq!(ssdeep_similarity(&ssdeep(half), &ssdeep(&replaced)), 60);
+
+        let text = words(50000, 4);
+        let text_edited = edited(text.clone(), 20000, &sample(500, 9));
+        assert_eq!(ssdeep_similarity(&ssdeep(&text_edited), &ssdeep(&text)), 99);
+        assert_eq!(ssdeep_similarity(&ssdeep(&text), &ssdeep(&small)), 0);
+    }
+
+    #[test]
+    fn identical_first_part_alone_is_not_a_match() {
+        assert_eq!(ssdeep_similarity("12:Fg6666666666666666666666666666666666666666666666666666666666666J:FV", "12:Fg6666666666666666666666666666666666666666666J:Ff"), 0);
+        assert_eq!(ssdeep_similarity("3:uG:uG", "3:uG:uG"), 100);
+    }
+}
@@ -0,0 +1,185 @@
+const WINDOW: usize = 5;
+const BUCKETS: usize = 128;
+const CODE_SIZE: usize = BUCKETS / 4;
+/// Shorter data gives too few triplets for a meaningful digest.
+const MIN_LENGTH: usize = 50;
+/// The digest of data with too little variety to fill the buckets.
+pub const TLSH_NULL: &str = "TNULL";
+
+/// Pearson hashing permutation.
+const V_TABLE: [u8; 256] = [
+    1, 87, 49, 12, 176, 178, 102, 166, 121, 193, 6, 84, 249, 230, 44, 163,
+    14, 197, 213, 181, 161, 85, 218, 80, 64, 239, 24, 226, 236, 142, 38, 200,
+    110, 177, 104, 103, 141, 253, 255, 50, 77, 101, 81, 18, 45, 96, 31, 222,
+    25, 107, 190, 70, 86, 237, 240, 34, 72, 242, 20, 214, 244, 227, 149, 235,
+    97, 234, 57, 22, 60, 250, 82, 175, 208, 5, 127, 199, 111, 62, 135, 248,
+    174, 169, 211, 58, 66, 154, 106, 195, 245, 171, 17, 187, 182, 179, 0, 243,
+    132, 56, 148, 75, 128, 133, 158, 100, 130, 126, 91, 13, 153, 246, 216, 219,
+    119, 68, 223, 78, 83, 88, 201, 99, 122, 11, 92, 32, 136, 114, 52, 10,
+    138, 30, 48, 183, 156, 35, 61, 26, 143, 74, 251, 94, 129, 162, 63, 152,
+    170, 7, 115, 167, 241, 206, 3, 150, 55, 59, 151, 220, 90, 53, 23, 131,
+    125, 173, 15, 238, 79, 95, 89, 16, 105, 137, 225, 224, 217, 160, 37, 123,
+    118, 73, 2, 157, 46, 116, 9, 145, 134, 228, 207, 212, 202, 215, 69, 229,
+    27, 188, 67, 124, 168, 252, 42, 4, 29, 108, 21, 247, 19, 205, 39, 203,
+    233, 40, 186, 147, 198, 192, 155, 33, 164, 191, 98, 204, 165, 180, 117, 76,
+    140, 36, 210, 172, 41, 54, 159, 8, 185, 232, 113, 196, 231, 47, 146, 120,
+    51, 65, 28, 144, 254, 221, 93, 189, 194, 139, 112, 43, 71, 109, 184, 209,
+];
+
+fn pearson(salt: u8, a: u8, b: u8, c: u8) -> u8 {
+    let h = V_TABLE[salt as usize];
+    let h = V_TABLE[(h ^ a) as usize];
+    let h = V_TABLE[(h ^ b) as usize];
+    V_TABLE[(h ^ c) as usize]
+}
+
+/// The length of the data on a logarithmic scale, wrapping at 256.
+fn length_code(len: usize) -> u8 {
+    let len = len as f64;
+    let code = if len <= 656.0 {
+        len.ln() / 1.5f64.ln()
+    } else if len <= 3199.0 {
+        len.ln() / 1.3f64.ln() - 8.72777
+    } else {
+        len.ln() / 1.1f64.ln() - 62.5472
+    };
+    (code.floor() as u64 & 0xff) as u8
+}
+
+fn swap_nibbles(byte: u8) -> u8 {
+    byte.rotate_left(4)
+}
+
+/// The TLSH style digest of some data: `T1` followed by 70 hex digits, or `TNULL` if the data is
+/// too short or too uniform. Byte triplets from a sliding window are counted into 128 buckets, and
+/// the digest records which quartile each bucket falls in, along with the data's length and the
+/// shape of the distribution.
+pub fn tlsh(data: &[u8]) -> String {
+    if data.len() < MIN_LENGTH {
+        return TLSH_NULL.to_owned();
+    }
+
+    let mut buckets = [0u32; 256];
+    let mut checksum = 0u8;
+    for window in data.windows(WINDOW) {
+        let [c4, c3, c2, c1, c0] = [window[0], window[1], window[2], window[3], window[4]];
+        checksum = pearson(0, c0, c1, checksum);
+        for (salt, a, b) in [(2, c1, c2), (3, c1, c3), (5, c2, c3), (7, c2, c4), (11, c1, c4), (13, c3, c4)] {
+            buckets[pearson(salt, c0, a, b) as usize] += 1;
+        }
+    }
+    let buckets = &buckets[..BUCKETS];
+
+    let mut sorted = buckets.to_vec();
+    sorted.sort_unstable();
+    let (q1, q2, q3) = (sorted[BUCKETS / 4 - 1], sorted[BUCKETS / 2 - 1], sorted[BUCKETS * 3 / 4 - 1]);
+    let nonzero = buckets.iter().filter(|b| **b > 0).count();
+    if q3 == 0 || nonzero <= BUCKETS / 2 {
+        return TLSH_NULL.to_owned();
+    }
+
+    let mut code = [0u8; CODE_SIZE];
+    for (i, byte) in code.iter_mut().enumerate() {
+        for j in 0..4 {
+            let count = buckets[4 * i + j];
+            let quartile = if count > q3 { 3 } else if count > q2 { 2 } else if count > q1 { 1 } else { 0 };
+            *byte |= quartile << (j * 2);
+        }
+    }
+
+    let q1_ratio = ((q1 as u64 * 100 / q3 as u64) % 16) as u8;
+    let q2_ratio = ((q2 as u64 * 100 / q3 as u64) % 16) as u8;
+
+    let mut digest = String::from("T1");
+    let header = [swap_nibbles(checksum), swap_nibbles(length_code(data.len())), (q1_ratio << 4) | q2_ratio];
+    for byte in header.iter().chain(code.iter().rev()) {
+        digest.push_str(&format!("{:02X}", byte));
+    }
+    digest
+}
+
+fn parse(digest: &str) -> Option<Vec<u8>> {
+    let hex = digest.strip_prefix("T1")?;
+    if hex.len() != (3 + CODE_SIZE) * 2 {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn mod_diff(a: u8, b: u8, range: u16) -> u16 {
+    let diff = (a as i16 - b as i16).unsigned_abs();
+    diff.min(range - diff)
+}
+
+/// Distance between two digests: 0 for identical data, growing with difference, with no upper
+/// bound. `None` if either digest is not a valid `T1` digest.
+pub fn tlsh_distance(a: &str, b: &str) -> Option<u32> {
+    let (a, b) = (parse(a)?, parse(b)?);
+    let mut distance = 0u32;
+
+    if a[0] != b[0] {
+        distance += 1;
+    }
+
+    let length = mod_diff(swap_nibbles(a[1]), swap_nibbles(b[1]), 256) as u32;
+    distance += if length <= 1 { length } else { length * 12 };
+
+    for shift in [0, 4] {
+        let ratio = mod_diff((a[2] >> shift) & 0xf, (b[2] >> shift) & 0xf, 16) as u32;
+        distance += if ratio <= 1 { ratio } else { (ratio - 1) * 12 };
+    }
+
+    for (x, y) in a[3..].iter().zip(&b[3..]) {
+        for j in 0..4 {
+            let diff = ((x >> (j * 2)) & 3).abs_diff((y >> (j * 2)) & 3) as u32;
+            distance += if diff == 3 { 6 } else { diff };
+        }
+    }
+    Some(distance)
+}
+
+/// Expected values from the reference TLSH implementation, with the default 128 buckets and a
+/// one byte checksum.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::{sample, words};
+
+    #[test]
+    fn digests() {
+        assert_eq!(tlsh(&sample(1000, 1)), "T1FF1198C412D5371C6BCE6EA35E039F90B08F2B507351C466D04AF53CAC8E16EC0BA409");
+        assert_eq!(tlsh(&sample(10000, 2)), "T14822BF2C6351FF7F41823583688413E0CFF617E89A257AA965913F672388B3ACC28592");
+        assert_eq!(tlsh(&words(50000, 4)), "T13F23129F83D5B2EB49A3011F6388E45317C4F3BC6C30FB62E2967A8A90491DA3D67547");
+        assert_eq!(tlsh(&b"abc".repeat(10)), TLSH_NULL);
+        assert_eq!(tlsh(&[0u8; 1000]), TLSH_NULL);
+    }
+
+    #[test]
+    fn distances() {
+        let small = sample(10000, 2);
+        let mut replaced = small.clone();
+        replaced[4000..6000].copy_from_slice(&sample(2000, 7));
+        let text = words(50000, 4);
+        let mut text_edited = text.clone();
+        text_edited[20000..20500].copy_from_slice(&sample(500, 9));
+
+        assert_eq!(tlsh_distance(&tlsh(&replaced), &tlsh(&small)), Some(57));
+        assert_eq!(tlsh_distance(&tlsh(&text_edited), &tlsh(&text)), Some(3));
+        assert_eq!(tlsh_distance(&tlsh(&text), &tlsh(&small)), Some(463));
+        assert_eq!(tlsh_distance(&tlsh(&small), &tlsh(&sample(1000, 1))), Some(496));
+        assert_eq!(tlsh_distance(&tlsh(&text), &tlsh(&text)), Some(0));
+        assert_eq!(tlsh_distance(TLSH_NULL, &tlsh(&text)), None);
+    }
+
+    #[test]
+    fn reference_distances() {
+        // From the reference implementation's example data
+        let yossivassa = "T11FA1B357F78913B236924271569EA6D1FB2C451C33668484552C812D33138B8C73FFCE";
+        let alice = "T145D18407A78523B35A030267671FA2C2F725402973629B25545EB43C3356679477F7FC";
+        let academy = "T1C8E1E8277B98A6255FA70063B70FBAE1F6618229332C9438084FB331178ADEE53B41D5";
+        assert_eq!(tlsh_distance(yossivassa, alice), Some(150));
+        assert_eq!(tlsh_distance(yossivassa, academy), Some(265));
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::environment::WadupModule;
+use crate::provenance::Provenance;
+
+/// What a module sits downstream of. A module that consumes anything only runs on blobs carved or
+/// submitted by one of its upstream modules, or hinted with one of its content types.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Consumes {
+    pub modules: Vec<String>,
+    pub content_types: Vec<String>,
+}
+
+impl Consumes {
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty() && self.content_types.is_empty()
+    }
+
+    pub fn accepts(&self, provenance: &Provenance) -> bool {
+        let parent = provenance.parent_module.as_deref();
+        let hint = provenance.hint.as_deref();
+        self.modules.iter().any(|m| Some(m.as_str()) == parent)
+            || self.content_types.iter().any(|t| Some(t.as_str()) == hint)
+    }
+}
+
+/// The modules a blob may be routed to. Root inputs and blobs nothing consumes go to the modules
+/// outside any pipeline; derived blobs with consumers go only to those consumers.
+pub fn candidates<'a>(modules: &'a [Arc<WadupModule>], provenance: &Provenance) -> Vec<&'a Arc<WadupModule>> {
+    if provenance.depth > 0 {
+        let consumers = modules.iter()
+            .filter(|m| m.consumes.accepts(provenance))
+            .collect::<Vec<_>>();
+        if !consumers.is_empty() {
+            return consumers;
+        }
+    }
+    modules.iter()
+        .filter(|m| m.consumes.is_empty())
+        .collect()
+}
+
+/// Checks that every consumed module exists and that no module is, through any chain of modules
+/// or produced content types, downstream of itself.
+pub fn validate(modules: &[Arc<WadupModule>]) -> Result<()> {
+    let mut upstream = BTreeMap::<&str, Vec<&str>>::new();
+    for module in modules {
+        let edges = upstream.entry(module.name.as_str()).or_default();
+        for name in &module.consumes.modules {
+            if !modules.iter().any(|m| &m.name == name) {
+                return Err(anyhow!("module {:?} consumes unknown module {:?}", module.name, name));
+            }
+            edges.push(name);
+        }
+        for content_type in &module.consumes.content_types {
+            edges.extend(modules.iter()
+                .filter(|m| m.produces.contains(content_type))
+                .map(|m| m.name.as_str()));
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        Active,
+        Done,
+    }
+
+    fn visit<'a>(name: &'a str, upstream: &BTreeMap<&'a str, Vec<&'a str>>, visits: &mut BTreeMap<&'a str, Visit>, path: &mut Vec<&'a str>) -> Result<()> {
+        match visits.get(name) {
+            Some(Visit::Done) => return Ok(()),
+            Some(Visit::Active) => {
+                let start = path.iter().position(|n| *n == name).unwrap_or_default();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(name);
+                cycle.reverse();
+                return Err(anyhow!("module graph has a cycle: {}", cycle.join(" -> ")));
+            },
+            None => {},
+        }
+        visits.insert(name, Visit::Active);
+        path.push(name);
+        for parent in upstream.get(name).into_iter().flatten() {
+            visit(parent, upstream, visits, path)?;
+        }
+        path.pop();
+        visits.insert(name, Visit::Done);
+        Ok(())
+    }
+
+    let mut visits = BTreeMap::new();
+    for name in upstream.keys() {
+        visit(name, &upstream, &mut visits, &mut Vec::new())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+    use wasmtime::{Engine, Module};
+    use super::*;
+
+    fn module(name: &str, modules: &[&str], content_types: &[&str], produces: &[&str]) -> Arc<WadupModule> {
+        let module = Module::new(&Engine::default(), "(module)").unwrap();
+        Arc::new(WadupModule {
+            name: name.to_owned(),
+            module: Arc::new(module),
+            routes: Vec::new(),
+            signatures: Vec::new(),
+            config: BTreeMap::new(),
+            run: true,
+            reduce: false,
+            consumes: Consumes {
+                modules: modules.iter().map(|m| m.to_string()).collect(),
+                content_types: content_types.iter().map(|t| t.to_string()).collect(),
+            },
+            produces: produces.iter().map(|p| p.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn accepts_pipelines() {
+        let modules = [
+            module("unpack.wasm", &[], &[], &["zip"]),
+            module("unzip.wasm", &["unpack.wasm"], &["zip"], &["text"]),
+            module("strings.wasm", &[], &["text"], &[]),
+        ];
+        assert!(validate(&modules).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_consumed_module() {
+        let modules = [module("unzip.wasm", &["unpack.wasm"], &[], &[])];
+        assert!(validate(&modules).is_err());
+    }
+
+    #[test]
+    fn rejects_module_cycles() {
+        let modules = [
+            module("a.wasm", &["c.wasm"], &[], &[]),
+            module("b.wasm", &["a.wasm"], &[], &[]),
+            module("c.wasm", &["b.wasm"], &[], &[]),
+        ];
+        let error = validate(&modules).unwrap_err().to_string();
+        assert!(error.contains("a.wasm -> b.wasm -> c.wasm -> a.wasm"), "{}", error);
+        assert!(validate(&[module("a.wasm", &["a.wasm"], &[], &[])]).is_err());
+    }
+
+    #[test]
+    fn rejects_content_type_cycles() {
+        let modules = [
+            module("a.wasm", &[], &["x"], &["y"]),
+            module("b.wasm", &[], &["y"], &["x"]),
+        ];
+        assert!(validate(&modules).is_err());
+    }
+
+    #[test]
+    fn routes_derived_blobs_to_consumers() {
+        let modules = [
+            module("unpack.wasm", &[], &[], &[]),
+            module("unzip.wasm", &["unpack.wasm"], &["zip"], &[]),
+        ];
+        let names = |candidates: Vec<&Arc<WadupModule>>| candidates.iter().map(|m| m.name.clone()).collect::<Vec<_>>();
+        let root = Provenance::root(Path::new("input/a.bin"));
+        assert_eq!(names(candidates(&modules, &root)), ["unpack.wasm"]);
+        assert_eq!(names(candidates(&modules, &root.child("unpack.wasm", 0))), ["unzip.wasm"]);
+        let mut hinted = root.child("other.wasm", 0);
+        assert_eq!(names(candidates(&modules, &hinted)), ["unpack.wasm"]);
+        hinted.hint = Some("zip".to_owned());
+        assert_eq!(names(candidates(&modules, &hinted)), ["unzip.wasm"]);
+    }
+}
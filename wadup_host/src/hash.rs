@@ -0,0 +1,138 @@
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::config::HexBytes;
+use crate::entropy::CHUNK_SIZE;
+use crate::fuzzy::{ssdeep, tlsh};
+use crate::provenance::Provenance;
+use crate::rows::Row;
+use crate::types::{BlobSource, DataValue};
+
+/// Schema of the rows emitted for every blob when `hashes` is configured.
+pub const HASHES_SCHEMA: &str = "wadup_hashes";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Ssdeep,
+    Tlsh,
+}
+
+impl HashAlgorithm {
+    /// The algorithm numbers used by `wadup_hash`.
+    pub fn from_id(id: u32) -> Result<HashAlgorithm> {
+        match id {
+            1 => Ok(HashAlgorithm::Md5),
+            2 => Ok(HashAlgorithm::Sha1),
+            3 => Ok(HashAlgorithm::Sha256),
+            4 => Ok(HashAlgorithm::Ssdeep),
+            5 => Ok(HashAlgorithm::Tlsh),
+            id => Err(anyhow!("unknown hash algorithm {}", id)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Ssdeep => "ssdeep",
+            HashAlgorithm::Tlsh => "tlsh",
+        }
+    }
+
+    /// Similarity digests are text and compared by distance rather than equality.
+    pub fn is_fuzzy(&self) -> bool {
+        matches!(self, HashAlgorithm::Ssdeep | HashAlgorithm::Tlsh)
+    }
+
+    /// The raw digest, or the text of a similarity digest.
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Md5 => Md5::digest(data).to_vec(),
+            HashAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            HashAlgorithm::Ssdeep => ssdeep(data).into_bytes(),
+            HashAlgorithm::Tlsh => tlsh(data).into_bytes(),
+        }
+    }
+
+    /// The digest of a source read a chunk at a time. Similarity digests need the data in one
+    /// slice, so a source that isn't contiguous is copied for them.
+    pub fn digest_at(&self, data: &(impl BlobSource + ?Sized)) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Md5 => digest_chunks::<Md5>(data),
+            HashAlgorithm::Sha1 => digest_chunks::<Sha1>(data),
+            HashAlgorithm::Sha256 => digest_chunks::<Sha256>(data),
+            HashAlgorithm::Ssdeep | HashAlgorithm::Tlsh => self.digest(&data.bytes()),
+        }
+    }
+
+    /// The digest as recorded in rows: hex, or the text of a similarity digest.
+    pub fn digest_text(&self, data: &[u8]) -> String {
+        let digest = self.digest(data);
+        if self.is_fuzzy() {
+            String::from_utf8_lossy(&digest).into_owned()
+        } else {
+            HexBytes(digest).to_string()
+        }
+    }
+}
+
+fn digest_chunks<D: Digest>(data: &(impl BlobSource + ?Sized)) -> Vec<u8> {
+    if let Some(data) = data.as_slice() {
+        return D::digest(data).to_vec();
+    }
+    let mut hasher = D::new();
+    let mut chunk = vec![0u8; CHUNK_SIZE.min(data.len())];
+    let mut position = 0;
+    while position < data.len() {
+        let count = data.read_at(position, &mut chunk);
+        if count == 0 {
+            break;
+        }
+        hasher.update(&chunk[..count]);
+        position += count;
+    }
+    hasher.finalize().to_vec()
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<HashAlgorithm> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "ssdeep" => Ok(HashAlgorithm::Ssdeep),
+            "tlsh" => Ok(HashAlgorithm::Tlsh),
+            _ => Err(anyhow!("unknown hash algorithm {:?}", s)),
+        }
+    }
+}
+
+/// The hashes row for a blob: where it came from, its size and a digest per algorithm.
+pub fn hashes_row(algorithms: &[HashAlgorithm], data: &[u8], provenance: &Provenance) -> Row {
+    let mut values = vec![
+        ("file_path".to_owned(), DataValue::StringValue(provenance.file_path.to_string_lossy().into_owned())),
+        ("offset".to_owned(), DataValue::Int64Value(provenance.offset as i64)),
+        ("depth".to_owned(), DataValue::Int64Value(provenance.depth as i64)),
+        ("size".to_owned(), DataValue::Int64Value(data.len() as i64)),
+    ];
+    for algorithm in algorithms {
+        values.push((algorithm.name().to_owned(), DataValue::StringValue(algorithm.digest_text(data))));
+    }
+    Row {
+        schema: HASHES_SCHEMA.to_owned(),
+        values,
+        tags: provenance.tags.snapshot(),
+    }
+}
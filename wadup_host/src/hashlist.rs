@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::{Result, anyhow};
+
+use crate::hash::HashAlgorithm;
+use crate::provenance::Provenance;
+use crate::rows::Row;
+use crate::types::DataValue;
+
+/// Schema of the rows emitted for every blob found in an allow or deny list.
+pub const KNOWN_SCHEMA: &str = "wadup_known";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    /// Known good: the blob is not dispatched to any module
+    Allow,
+    /// Known bad: the blob is flagged and processed as usual
+    Deny,
+}
+
+impl ListKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ListKind::Allow => "allow",
+            ListKind::Deny => "deny",
+        }
+    }
+}
+
+/// Hex digests by algorithm, each with the name of the list file it came from.
+#[derive(Default)]
+struct HashList {
+    hashes: HashMap<HashAlgorithm, HashMap<String, Arc<str>>>,
+}
+
+impl HashList {
+    fn load(paths: &[PathBuf]) -> Result<HashList> {
+        let mut list = HashList::default();
+        for path in paths {
+            let text = fs::read_to_string(path).map_err(|e| anyhow!("unable to read hash list {:?}: {}", path, e))?;
+            let source = Arc::<str>::from(path.file_name().unwrap_or(path.as_os_str()).to_string_lossy());
+            for (algorithm, digest) in parse_list(&text) {
+                list.hashes.entry(algorithm).or_default().insert(digest, source.clone());
+            }
+        }
+        Ok(list)
+    }
+
+    fn get(&self, algorithm: HashAlgorithm, digest: &str) -> Option<&Arc<str>> {
+        self.hashes.get(&algorithm)?.get(digest)
+    }
+}
+
+/// Splits a CSV line, removing the quotes around quoted fields.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// The algorithm of a bare hex digest, from its length.
+fn algorithm_of(digest: &str) -> Option<HashAlgorithm> {
+    if !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match digest.len() {
+        32 => Some(HashAlgorithm::Md5),
+        40 => Some(HashAlgorithm::Sha1),
+        64 => Some(HashAlgorithm::Sha256),
+        _ => None,
+    }
+}
+
+/// The algorithm a CSV column holds, from its header name compared case-insensitively and without
+/// dashes, so NSRL's `"SHA-1"` is `sha1`. Similarity digests can't be looked up and are ignored.
+fn column_algorithm(name: &str) -> Option<HashAlgorithm> {
+    let name = name.trim_start_matches('\u{feff}').trim().to_ascii_lowercase().replace('-', "");
+    name.parse::<HashAlgorithm>().ok().filter(|a| !a.is_fuzzy())
+}
+
+/// Reads either a CSV file whose header names hash columns, such as NSRL's
+/// `"SHA-1","MD5","CRC32",...`, or plain text with a hex digest at the start of each line. Lines
+/// that are not valid digests, including `#` comments, are ignored.
+fn parse_list(text: &str) -> Vec<(HashAlgorithm, String)> {
+    let mut lines = text.lines();
+    let header = lines.next().map(split_csv).unwrap_or_default();
+    let columns = header.iter()
+        .enumerate()
+        .filter_map(|(i, name)| Some((i, column_algorithm(name)?)))
+        .collect::<Vec<_>>();
+
+    if !columns.is_empty() {
+        return lines
+            .map(split_csv)
+            .flat_map(|fields| {
+                columns.iter()
+                    .filter_map(|(i, algorithm)| {
+                        let digest = fields.get(*i)?.trim().to_ascii_lowercase();
+                        (algorithm_of(&digest) == Some(*algorithm)).then_some((*algorithm, digest))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    text.lines()
+        .filter_map(|line| {
+            let digest = line.split([' ', '\t', ',']).next()?.trim_matches('"').to_ascii_lowercase();
+            Some((algorithm_of(&digest)?, digest))
+        })
+        .collect()
+}
+
+/// A blob found in a list: which list, and the digest and list file that matched.
+pub struct Known {
+    pub kind: ListKind,
+    pub algorithm: HashAlgorithm,
+    pub digest: String,
+    pub source: Arc<str>,
+}
+
+/// The algorithms a list can hold, in the order a blob's digests are looked up, so that a blob
+/// listed under several digests is always reported with the strongest.
+const CHECK_ORDER: [HashAlgorithm; 3] = [HashAlgorithm::Sha256, HashAlgorithm::Sha1, HashAlgorithm::Md5];
+
+/// The allow and deny lists loaded at startup.
+pub struct HashLists {
+    allow: HashList,
+    deny: HashList,
+    algorithms: Vec<HashAlgorithm>,
+}
+
+impl HashLists {
+    /// Loads the lists, or returns `None` if none are configured.
+    pub fn load(allow: &[PathBuf], deny: &[PathBuf]) -> Result<Option<HashLists>> {
+        if allow.is_empty() && deny.is_empty() {
+            return Ok(None);
+        }
+        let allow = HashList::load(allow)?;
+        let deny = HashList::load(deny)?;
+        let algorithms = CHECK_ORDER.into_iter()
+            .filter(|algorithm| allow.hashes.contains_key(algorithm) || deny.hashes.contains_key(algorithm))
+            .collect();
+        Ok(Some(HashLists { allow, deny, algorithms }))
+    }
+
+    /// Looks the blob up in the lists, hashing it only with the algorithms the lists use. A blob
+    /// in both lists is treated as denied.
+    pub fn check(&self, data: &[u8]) -> Option<Known> {
+        let digests = self.algorithms.iter()
+            .map(|algorithm| (*algorithm, algorithm.digest_text(data)))
+            .collect::<Vec<_>>();
+        [(ListKind::Deny, &self.deny), (ListKind::Allow, &self.allow)].into_iter()
+            .find_map(|(kind, list)| {
+                digests.iter().find_map(|(algorithm, digest)| {
+                    let source = list.get(*algorithm, digest)?;
+                    Some(Known { kind, algorithm: *algorithm, digest: digest.clone(), source: source.clone() })
+                })
+            })
+    }
+}
+
+/// The row for a listed blob: where it came from, the list it was found in and what was done.
+pub fn known_row(known: &Known, provenance: &Provenance) -> Row {
+    let action = match known.kind {
+        ListKind::Allow => "skipped",
+        ListKind::Deny => "flagged",
+    };
+    Row {
+        schema: KNOWN_SCHEMA.to_owned(),
+        values: vec![
+            ("file_path".to_owned(), DataValue::StringValue(provenance.file_path.to_string_lossy().into_owned())),
+            ("offset".to_owned(), DataValue::Int64Value(provenance.offset as i64)),
+            ("depth".to_owned(), DataValue::Int64Value(provenance.depth as i64)),
+            ("list".to_owned(), DataValue::StringValue(known.kind.name().to_owned())),
+            ("source".to_owned(), DataValue::StringValue(known.source.to_string())),
+            ("algorithm".to_owned(), DataValue::StringValue(known.algorithm.name().to_owned())),
+            ("digest".to_owned(), DataValue::StringValue(known.digest.clone())),
+            ("action".to_owned(), DataValue::StringValue(action.to_owned())),
+        ],
+        tags: provenance.tags.snapshot(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nsrl_csv() {
+        let text = concat!(
+            "\u{feff}\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\",\"FileSize\",\"ProductCode\",\"OpSystemCode\",\"SpecialCode\"\r\n",
+            "\"0000002D9D62AEBE1E0E9DB6C4C4C7C16A163D2C\",\"1D6EBB5A789ABD108FF578263E1F40F3\",\"FFFFFFFF\",\"_sfx_0024._p\",4109,21000,\"358\",\"\"\r\n",
+            "\"00000142988AFA836117B1B572FAE4713F200567\",\"9B3702B0E788C6D62996392FE3C9786A\",\"05E566DF\",\"J0180794.JPG\",32768,16848,\"358\",\"\"\r\n",
+        );
+        assert_eq!(parse_list(text), vec![
+            (HashAlgorithm::Sha1, "0000002d9d62aebe1e0e9db6c4c4c7c16a163d2c".to_owned()),
+            (HashAlgorithm::Md5, "1d6ebb5a789abd108ff578263e1f40f3".to_owned()),
+            (HashAlgorithm::Sha1, "00000142988afa836117b1b572fae4713f200567".to_owned()),
+            (HashAlgorithm::Md5, "9b3702b0e788c6d62996392fe3c9786a".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn parses_plain_text() {
+        let text = "# comment\nd41d8cd98f00b204e9800998ecf8427e  empty.bin\nnot a digest\n";
+        assert_eq!(parse_list(text), vec![(HashAlgorithm::Md5, "d41d8cd98f00b204e9800998ecf8427e".to_owned())]);
+    }
+
+    #[test]
+    fn reports_strongest_digest() {
+        let data = b"listed";
+        let mut allow = HashList::default();
+        for algorithm in [HashAlgorithm::Md5, HashAlgorithm::Sha256, HashAlgorithm::Sha1] {
+            allow.hashes.entry(algorithm).or_default().insert(algorithm.digest_text(data), Arc::from("allow.txt"));
+        }
+        let algorithms = CHECK_ORDER.to_vec();
+        let lists = HashLists { allow, deny: HashList::default(), algorithms };
+        let known = lists.check(data).unwrap();
+        assert_eq!(known.kind, ListKind::Allow);
+        assert_eq!(known.algorithm, HashAlgorithm::Sha256);
+    }
+}
@@ -1,17 +1,23 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use std::sync::mpmc::Sender;
-use wasmtime::{Module, Store, Trap};
-use anyhow::Result;
+use wasmtime::{Store, Trap};
+use anyhow::{Result, anyhow};
 use uuid::Uuid;
 
 use crate::context::Context;
 use crate::types::Blob;
-use crate::environment::Environment;
+use crate::environment::{Environment, WadupModule};
+use crate::output::OutputBuffer;
+use crate::provenance::Provenance;
+use crate::quota::{QuotaExceeded, Usage};
+use crate::signature;
 
 pub enum JobOrDie {
     Job(Job),
+    Inspect(Box<Inspection>),
     Die,
 }
 
@@ -28,11 +34,40 @@ pub struct JobResult {
     pub id: Uuid,
     pub message: Option<String>,
     pub error: Option<String>,
+    pub filtered: bool,
 }
 
 pub enum JobTracking {
+    InputQueued(PathBuf),
+    InputDispatched(PathBuf),
     JobInfo(JobInfo),
+    JobStarted(Uuid),
     JobResult(JobResult),
+    Mapped(u64),
+    Unmapped(u64),
+    /// Lines for stdout, printed by the tracker above the progress view
+    Output(String),
+    /// A blob was found in an allow list and not dispatched
+    AllowListed,
+    /// A blob was found in a deny list, with a description of where
+    DenyListed(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobKind {
+    /// Runs `wadup_run` on a blob
+    Run,
+    /// Runs `wadup_reduce` once after every other job has finished
+    Reduce,
+}
+
+impl JobKind {
+    pub fn entry_point(&self) -> &'static str {
+        match self {
+            JobKind::Run => "wadup_run",
+            JobKind::Reduce => "wadup_reduce",
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -41,49 +76,146 @@ pub struct Job {
     pub job_sender: Sender<JobOrDie>,
     pub tracking_sender: Sender<JobTracking>,
     pub environment: Arc<Environment>,
-    pub module: Arc<Module>,
+    pub module: Arc<WadupModule>,
+    pub blob: Blob,
+    pub provenance: Arc<Provenance>,
+    pub kind: JobKind,
+    /// The modules that invoked this job through `wadup_invoke`, outermost first
+    pub invoked_by: Vec<String>,
+}
+
+/// A blob waiting for the host services that run before it is queued to modules, tracked like a
+/// job so the run doesn't finish while it is pending.
+pub struct Inspection {
+    pub info: JobInfo,
+    pub job_sender: Sender<JobOrDie>,
+    pub tracking_sender: Sender<JobTracking>,
+    pub environment: Arc<Environment>,
     pub blob: Blob,
+    pub provenance: Provenance,
+    /// The modules to queue the blob to, or routed when none are given
+    pub modules: Option<Vec<Arc<WadupModule>>>,
 }
 
 pub fn process(job: Job) -> Result<JobResult> {
+    if job.kind == JobKind::Run && !signature::matches_any(&job.module.signatures, job.blob.as_ref()) {
+        return Ok(JobResult {
+            id: job.info.id,
+            message: Some(format!("{} {:?} filtered by signature", job.info.module_name, job.info.file_path)),
+            error: None,
+            filtered: true,
+        });
+    }
+
+    let fuel = job.environment.config.fuel;
+    let (store, error) = call(&job, fuel, Default::default())?;
+    if let Some(e) = error {
+        let _ = job.tracking_sender.send(JobTracking::Output(format!("ERROR: {}", e)));
+    }
+
+    let fuel_end = store.get_fuel()?;
+    let fuel_used = fuel - fuel_end;
+
+    let message = format!("{} {:?} memory used: {}, table used: {}, fuel used: {}", job.info.module_name, job.info.file_path, store.data().memory_used, store.data().table_used, fuel_used);
+    Ok(JobResult {
+        id: job.info.id,
+        message: Some(message),
+        error: None,
+        filtered: false,
+    })
+}
+
+/// Instantiates the job's module and calls its entry point with the given fuel, counting what it
+/// produces against `usage`. An error raised by the module is returned alongside the store rather
+/// than failing the call.
+pub fn call(job: &Job, fuel: u64, usage: Arc<Mutex<Usage>>) -> Result<(Store<Context>, Option<String>)> {
+    let memory_limit = job.environment.config.memory_limit()?;
     let mut store = Store::new(&job.environment.engine, Context {
         job: job.clone(),
-        input: job.blob,
+        input: job.blob.clone(),
         output: Default::default(),
         schema: Default::default(),
         column: Default::default(),
         metadata: Default::default(),
-        memory_limit: job.environment.args.memory,
+        cursors: Default::default(),
+        invocations: Default::default(),
+        searches: Default::default(),
+        usage,
+        memory_limit,
         memory_used: Default::default(),
-        table_limit: job.environment.args.table,
+        table_limit: job.environment.config.table,
         table_used: Default::default(),
     });
 
-    store.set_fuel(job.environment.args.fuel)?;
+    store.set_fuel(fuel)?;
     store.limiter(|s| s);
 
-    let instance = job.environment.linker.instantiate(&mut store, &job.module)?;
+    let instance = job.environment.linker.instantiate(&mut store, &job.module.module)?;
     
-    let func = instance.get_typed_func::<(), ()>(&mut store, "wadup_run")?;
+    let func = instance.get_typed_func::<(), ()>(&mut store, job.kind.entry_point())?;
 
-    if let Some(e) = func.call(&mut store, ()).err() {
-        let e = if let Some(e) = e.downcast_ref::<Trap>() {
+    let error = func.call(&mut store, ()).err().map(|e| {
+        if let Some(e) = e.downcast_ref::<QuotaExceeded>() {
+            e.to_string()
+        } else if let Some(e) = e.downcast_ref::<Trap>() {
             e.to_string()
         } else if let Some(e) = e.downcast_ref::<String>() {
             e.to_string()
         } else {
             e.to_string()
-        };
-        println!("ERROR: {}", e);
+        }
+    });
+
+    Ok((store, error))
+}
+
+/// What a module run synchronously through `wadup_invoke` left behind.
+pub struct Invocation {
+    pub outputs: Vec<OutputBuffer>,
+    pub tags: BTreeMap<String, String>,
+    pub error: Option<String>,
+    pub fuel_used: u64,
+}
+
+/// Runs a module on a blob derived from the caller's job, with at most the given fuel, and
+/// collects its output buffers and the tags it set. Rows and carves from the module are handled
+/// as for any other job, and everything it produces counts against the caller's quotas.
+///
+/// Fails if the module is already in the chain of invocations or the chain would grow past the
+/// configured depth.
+pub fn invoke(caller: &Job, module: Arc<WadupModule>, blob: Blob, provenance: Provenance, fuel: u64, usage: Arc<Mutex<Usage>>) -> Result<Invocation> {
+    let mut invoked_by = caller.invoked_by.clone();
+    invoked_by.push(caller.info.module_name.clone());
+    if invoked_by.contains(&module.name) {
+        return Err(anyhow!("module {:?} is already being invoked by {}", module.name, invoked_by.join(" -> ")));
+    }
+    if invoked_by.len() > caller.environment.config.invoke_depth {
+        return Err(anyhow!("invoke depth limited to {}", caller.environment.config.invoke_depth));
     }
 
-    let fuel_end = store.get_fuel()?;
-    let fuel_used = job.environment.args.fuel - fuel_end;
+    let job = Job {
+        info: JobInfo {
+            id: Uuid::new_v4(),
+            module_name: module.name.clone(),
+            file_path: None,
+        },
+        job_sender: caller.job_sender.clone(),
+        tracking_sender: caller.tracking_sender.clone(),
+        environment: caller.environment.clone(),
+        module,
+        blob,
+        provenance: Arc::new(provenance),
+        kind: JobKind::Run,
+        invoked_by,
+    };
 
-    let message = format!("{} {:?} memory used: {}, table used: {}, fuel used: {}", job.info.module_name, job.info.file_path, store.data().memory_used, store.data().table_used, fuel_used);
-    Ok(JobResult {
-        id: job.info.id,
-        message: Some(message),
-        error: None,
+    let (store, error) = call(&job, fuel, usage)?;
+    let fuel_used = fuel - store.get_fuel()?;
+    let outputs = std::mem::take(&mut *store.data().output.lock().map_err(|_| anyhow!("invoke unable to lock mutex"))?);
+    Ok(Invocation {
+        outputs,
+        tags: job.provenance.tags.snapshot(),
+        error,
+        fuel_used,
     })
-}
\ No newline at end of file
+}
@@ -6,9 +6,11 @@ use std::fs::{self, File};
 use wasmtime::{Engine, Module};
 use std::time::UNIX_EPOCH;
 
+use crate::signature::{Signature, read_signature_section};
+
 pub fn read_u64_le<R: Read>(input: &mut R) -> Result<u64> {
     let mut buf = [0u8; 8];
-    input.read(&mut buf)?;
+    input.read_exact(&mut buf)?;
     Ok(u64::from_le_bytes(buf))
 }
 
@@ -38,6 +40,20 @@ pub fn read_compiled(module_compiled_path: &Path, engine_hash: u64, module_modif
     Ok(result)
 }
 
+pub fn load_signatures(module_path: &Path) -> Result<Vec<Signature>> {
+    read_signature_section(&fs::read(module_path)?)
+        .map_err(|e| anyhow!("unable to read signatures from {:?}: {}", module_path, e))
+}
+
+/// The `.wasm` files in the modules directory.
+pub fn module_paths(modules_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(modules_path)?
+        .filter_map(|p| p.ok() )
+        .map(|p| p.path())
+        .filter(|p| p.extension().map(|s| s == "wasm").unwrap_or(false))
+        .collect())
+}
+
 pub fn load_module(engine: &Engine, module_path: &PathBuf) -> Result<(String, Module)> {
     let mut hasher = DefaultHasher::new();
     engine.precompile_compatibility_hash().hash(&mut hasher);
@@ -48,10 +64,10 @@ pub fn load_module(engine: &Engine, module_path: &PathBuf) -> Result<(String, Mo
     let module_compiled_path = format!("{}_precompiled", module_path.display());
     let module_compiled_path = Path::new(&module_compiled_path);
 
-    let module = if let Some(module_compiled) = read_compiled(module_compiled_path, engine_hash, module_modified).ok() {
-        unsafe { Module::deserialize(&engine, &module_compiled) }?
+    let module = if let Ok(module_compiled) = read_compiled(module_compiled_path, engine_hash, module_modified) {
+        unsafe { Module::deserialize(engine, &module_compiled) }?
     } else {
-        let module = Module::from_file(&engine, module_path)?;
+        let module = Module::from_file(engine, module_path)?;
         let mut file = File::create(module_compiled_path)?;
         write_u64_le(&mut file, engine_hash)?;
         write_u64_le(&mut file, module_modified)?;
@@ -1,58 +1,129 @@
 /*
 TODO:
-  - Not yet processing new data output from modules, only carved data
   - Track lineage what metadata was derived from what file; what file was derived from what file; etc.
   - Limit recursion
  */
 
-#![feature(try_blocks)]
 #![feature(mpmc_channel)]
 
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::sync::mpmc::{Sender, Receiver, channel};
+use std::time::Duration;
+use std::sync::mpmc::{Sender, Receiver, RecvTimeoutError, channel};
 use anyhow::{Result, anyhow};
 use uuid::Uuid;
-use wasmtime::Module;
 
 mod bindings;
 mod carve;
+mod config;
 mod context;
+mod decompress;
+mod dispatch;
+mod encoding;
+mod entropy;
 mod environment;
+mod filetype;
+mod fuzzy;
+mod graph;
+mod hash;
+mod hashlist;
 mod job;
 mod load;
+mod manifest;
+mod progress;
+mod provenance;
+mod quota;
+mod routing;
+mod rows;
+mod search;
+mod similar;
+mod signature;
+mod transform;
 mod types;
 mod mmap;
+mod output;
+mod yara;
 
+use clap::Parser;
+use config::{Cli, Command, Config, ConfigCommand};
+use dispatch::{dispatch, dispatch_reduce, process_inspection, route};
 use environment::Environment;
-use job::{process, Job, JobInfo, JobOrDie, JobResult, JobTracking};
+use job::{process, JobOrDie, JobResult, JobTracking};
 use types::Blob;
 use mmap::Mmap;
+use progress::Progress;
+use provenance::Provenance;
 
-fn tracker_thread(thread_count: usize, tracking_receiver: Receiver<JobTracking>, job_sender: Sender<JobOrDie>) {
+fn tracker_thread(
+    thread_count: usize,
+    mut progress: Progress,
+    environment: Arc<Environment>,
+    tracking_receiver: Receiver<JobTracking>,
+    tracking_sender: Sender<JobTracking>,
+    job_sender: Sender<JobOrDie>,
+) {
     let mut job_ids = HashSet::<Uuid>::new();
+    let mut inputs_pending = HashSet::<PathBuf>::new();
+    let mut reduced = false;
     loop {
-        match tracking_receiver.recv() {
+        match tracking_receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(JobTracking::InputQueued(file_path)) => {
+                progress.input_queued(&file_path);
+                inputs_pending.insert(file_path);
+            },
+            Ok(JobTracking::InputDispatched(file_path)) => {
+                progress.input_dispatched(&file_path);
+                inputs_pending.remove(&file_path);
+            },
             Ok(JobTracking::JobInfo(info)) => {
+                progress.job_info(&info);
                 job_ids.insert(info.id);
             },
+            Ok(JobTracking::JobStarted(id)) => {
+                progress.job_started(id);
+            },
             Ok(JobTracking::JobResult(result)) => {
-                println!("RESULT: {:?}", result);
+                progress.job_result(&result);
+                progress.println(&format!("RESULT: {:?}", result));
                 job_ids.remove(&result.id);
-                if job_ids.len() == 0 {
-                    for _ in 0..thread_count {
-                        job_sender.send(JobOrDie::Die).unwrap();
-                    }
-                    return;
-                }
             },
-            Err(_) => {
+            Ok(JobTracking::Mapped(len)) => {
+                progress.mapped(len);
+            },
+            Ok(JobTracking::Unmapped(len)) => {
+                progress.unmapped(len);
+            },
+            Ok(JobTracking::Output(lines)) => {
+                progress.println(&lines);
+            },
+            Ok(JobTracking::AllowListed) => {
+                progress.allow_listed();
+            },
+            Ok(JobTracking::DenyListed(description)) => {
+                progress.deny_listed();
+                progress.println(&format!("EVENT: {}", description));
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => {
                 return;
             },
         }
+        if inputs_pending.is_empty() && job_ids.is_empty() && !reduced {
+            // Every row has been emitted, so reduce modules see the complete result
+            reduced = true;
+            job_ids.extend(dispatch_reduce(&environment, &job_sender, &tracking_sender));
+        }
+        if inputs_pending.is_empty() && job_ids.is_empty() {
+            progress.finish();
+            for _ in 0..thread_count {
+                job_sender.send(JobOrDie::Die).unwrap();
+            }
+            return;
+        }
+        progress.tick();
     }
 }
 
@@ -61,6 +132,7 @@ fn process_thread(job_receiver: Receiver<JobOrDie>, tracking_sender: Sender<JobT
         match job_receiver.recv() {
             Ok(JobOrDie::Job(job)) => {
                 let job_id = job.info.id;
+                tracking_sender.send(JobTracking::JobStarted(job_id)).unwrap();
                 tracking_sender.send(JobTracking::JobResult(match process(job) {
                     Ok(result) => result,
                     Err(err) => {
@@ -68,10 +140,16 @@ fn process_thread(job_receiver: Receiver<JobOrDie>, tracking_sender: Sender<JobT
                             id: job_id,
                             message: None,
                             error: Some(err.to_string()),
+                            filtered: false,
                         }
                     },
                 })).unwrap();
             },
+            Ok(JobOrDie::Inspect(inspection)) => {
+                let job_id = inspection.info.id;
+                tracking_sender.send(JobTracking::JobStarted(job_id)).unwrap();
+                tracking_sender.send(JobTracking::JobResult(process_inspection(inspection))).unwrap();
+            },
             _ => {
                 return;
             }
@@ -79,93 +157,117 @@ fn process_thread(job_receiver: Receiver<JobOrDie>, tracking_sender: Sender<JobT
     }
 }
 
+fn map_input(
+    file_path: &Path,
+    environment: &Environment,
+    tracking_sender: &Sender<JobTracking>,
+) -> Result<Blob> {
+    let file_handle = File::open(file_path)?;
+
+    let file_len = file_handle.metadata()?.len();
+    if file_len > environment.config.mapped.0 {
+        return Err(anyhow!("File {:?} larger than maximum mapped memory", file_path));
+    }
+
+    let input_blob : Blob = Arc::new(Mmap::new(&file_handle, file_len, environment.mapped.clone(), tracking_sender.clone())?);
+    Ok(input_blob)
+}
+
 fn input_thread(
-    jobs: Vec<(PathBuf, Vec<(JobInfo, Arc<Module>)>)>, 
+    inputs: Vec<PathBuf>,
     environment: Arc<Environment>,
     job_sender: Sender<JobOrDie>,
     tracking_sender: Sender<JobTracking>,
 ) {
-    let (free_sender, free_receiver) = channel::<u64>();
-    let mut mapped = 0u64;
-    for (file_path, file_jobs) in &jobs {
-        let result : Result<Blob> = try {
-            let file_handle = File::open(&file_path)?;
-            
-            let file_len = file_handle.metadata()?.len();
-            if file_len > environment.args.mapped {
-                Err(anyhow!("File {:?} larger than maximum mapped memory", file_path))?;
-            }
-            while mapped + file_len > environment.args.mapped {
-                mapped -= free_receiver.recv()?;
-            }
-
-            mapped += file_len;
-            let input_blob : Blob = Arc::new(Mmap::new(&file_handle, file_len, free_sender.clone())?);
-            input_blob
-        };
-        match result {
+    for file_path in inputs {
+        match map_input(&file_path, &environment, &tracking_sender) {
             Ok(input_blob) => {
-                for (info, module) in file_jobs {
-                    job_sender.send(JobOrDie::Job(Job {
-                        info: info.clone(),
-                        job_sender: job_sender.clone(),
-                        tracking_sender: tracking_sender.clone(),
-                        environment: environment.clone(),
-                        module: module.clone(),
-                        blob: input_blob.clone(),
-                    })).unwrap();
-                }
+                let provenance = Provenance::root(&file_path);
+                dispatch(&environment, &job_sender, &tracking_sender, input_blob, provenance);
             },
             Err(err) => {
-                let error = format!("Failed to create jobs from {:?}: {}", file_path, err);
-                for (info, _module) in file_jobs {
-                    tracking_sender.send(JobTracking::JobResult(JobResult {
-                        id: info.id,
-                        message: None,
-                        error: Some(error.clone()),
-                    })).unwrap();
-                }
+                tracking_sender.send(JobTracking::JobResult(JobResult {
+                    id: Uuid::new_v4(),
+                    message: None,
+                    error: Some(format!("Failed to create jobs from {:?}: {}", file_path, err)),
+                    filtered: false,
+                })).unwrap();
             }
         }
+        tracking_sender.send(JobTracking::InputDispatched(file_path)).unwrap();
     }
 }
 
-fn create_jobs_one_shot(environment: Arc<Environment>, tracking_sender: Sender<JobTracking>) -> Result<Vec<(PathBuf, Vec<(JobInfo, Arc<Module>)>)>> {
-    let file_paths = fs::read_dir(&environment.args.input)?
+fn list_inputs(environment: &Environment) -> Result<Vec<PathBuf>> {
+    let file_paths = fs::read_dir(&environment.config.input)?
         .filter_map(|p| p.ok() )
-        .map(|p| p.path());
-
-    let jobs = file_paths.map(|file_path| {
-        (file_path.clone(), environment.modules.iter().map(move |(module_name, module)| {
-            (JobInfo {
-                id: Uuid::new_v4(),
-                module_name: module_name.clone(),
-                file_path: Some(file_path.clone()),
-            }, module.clone())
-        }).collect::<Vec<_>>())
-    }).collect::<Vec<_>>();
-
-    for (_file_path, file_jobs) in &jobs {
-        for (info, _module) in file_jobs {
-            tracking_sender.send(JobTracking::JobInfo(info.clone())).unwrap();
+        .map(|p| p.path())
+        .collect::<Vec<_>>();
+    Ok(file_paths)
+}
+
+fn dry_run(environment: &Environment, inputs: &[PathBuf]) -> Result<()> {
+    for file_path in inputs {
+        let file_handle = File::open(file_path)?;
+        let data = unsafe { memmap2::Mmap::map(&file_handle)? };
+        let mut provenance = Provenance::root(file_path);
+        provenance.file_type = Some(filetype::identify(&data));
+        if let Some(rules) = &environment.yara {
+            provenance.yara = rules.scan(&data)?;
         }
+        let modules = route(environment, &data, &provenance)
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>();
+        println!("ROUTE: {:?} -> [{}]", file_path, modules.join(", "));
     }
-
-    Ok(jobs)
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let (job_sender, job_receiver) = channel::<JobOrDie>();
     let (tracking_sender, tracking_receiver) = channel::<JobTracking>();
 
-    let environment = Arc::new(Environment::create()?);
-    let thread_count = environment.args.threads;
+    let cli = Cli::parse();
+    let config = Config::load(&cli)?;
 
-    let jobs = create_jobs_one_shot(environment.clone(), tracking_sender.clone())?;
+    match cli.command {
+        Some(Command::Config { command: ConfigCommand::Print }) => {
+            print!("{}", config.to_toml()?);
+            return Ok(());
+        },
+        Some(Command::Similar { algorithm, threshold }) => {
+            let results = config.results.as_deref().ok_or_else(|| anyhow!("similar needs a results file, given with --results"))?;
+            return similar::similar_command(results, algorithm, threshold);
+        },
+        None => {},
+    }
+
+    let environment = Arc::new(Environment::create(config)?);
+    let thread_count = environment.config.threads;
+    let progress = Progress::new(environment.config.progress, environment.config.mapped.0);
+
+    let inputs = list_inputs(&environment)?;
+
+    if cli.dry_run {
+        for warning in &environment.warnings {
+            println!("WARNING: {}", warning);
+        }
+        return dry_run(&environment, &inputs);
+    }
+
+    // Sent through the tracker so they print above the progress view
+    for warning in &environment.warnings {
+        tracking_sender.send(JobTracking::Output(format!("WARNING: {}", warning)))?;
+    }
+    for file_path in &inputs {
+        tracking_sender.send(JobTracking::InputQueued(file_path.clone()))?;
+    }
 
+    let tracker_environment = environment.clone();
     thread::scope(|s| {
         s.spawn(|| {
-            tracker_thread(thread_count, tracking_receiver, job_sender.clone());
+            tracker_thread(thread_count, progress, tracker_environment, tracking_receiver, tracking_sender.clone(), job_sender.clone());
         });
 
         for _ in 0..thread_count {
@@ -175,7 +277,7 @@ fn main() -> Result<()> {
         }
 
         s.spawn(|| {
-            input_thread(jobs, environment, job_sender.clone(), tracking_sender.clone());
+            input_thread(inputs, environment, job_sender.clone(), tracking_sender.clone());
         });
     });
 
@@ -0,0 +1,32 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::graph::Consumes;
+use crate::routing::Route;
+use crate::signature::Signature;
+
+/// Optional `<module>.toml` shipped next to `<module>.wasm`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest {
+    pub route: Vec<Route>,
+    pub signature: Vec<Signature>,
+    /// Default configuration parameters, overridden by `module_args` and `--module-arg`
+    pub config: BTreeMap<String, toml::Value>,
+    /// Upstream modules and content types this module runs on, instead of root inputs
+    pub consumes: Consumes,
+    /// Content types this module carves or submits, used to check the module graph for cycles
+    pub produces: Vec<String>,
+}
+
+pub fn load_manifest(module_path: &Path) -> Result<Manifest> {
+    let manifest_path = module_path.with_extension("toml");
+    if !manifest_path.exists() {
+        return Ok(Manifest::default());
+    }
+    let text = fs::read_to_string(&manifest_path).map_err(|e| anyhow!("unable to read manifest {:?}: {}", manifest_path, e))?;
+    toml::from_str(&text).map_err(|e| anyhow!("unable to parse manifest {:?}: {}", manifest_path, e))
+}
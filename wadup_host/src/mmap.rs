@@ -1,31 +1,169 @@
 use std::fs::File;
+use std::sync::{Arc, Condvar, Mutex};
 use std::sync::mpmc::Sender;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+
+use crate::job::JobTracking;
+use crate::types::{Blob, BlobSource};
+
+/// Memory held by blobs, mapped input files and data derived from them alike, against the
+/// `mapped` limit. The input thread waits for room before mapping another file. Derived blobs are
+/// created on worker threads, whose jobs are what frees memory, so rather than wait they fail when
+/// there is no room, and otherwise hold off further inputs until they are released.
+pub struct MappedBudget {
+    limit: u64,
+    used: Mutex<u64>,
+    released: Condvar,
+}
+
+impl MappedBudget {
+    pub fn new(limit: u64) -> MappedBudget {
+        MappedBudget { limit, used: Mutex::new(0), released: Condvar::new() }
+    }
+
+    /// Waits until `len` more bytes fit within the limit, then charges them.
+    fn acquire(&self, len: u64) -> Result<()> {
+        if len > self.limit {
+            return Err(anyhow!("larger than maximum mapped memory"));
+        }
+        let used = self.used.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+        let mut used = self.released.wait_while(used, |used| *used + len > self.limit).map_err(|_| anyhow!("unable to lock mutex"))?;
+        *used += len;
+        Ok(())
+    }
+
+    /// Charges `len` more bytes if they fit within the limit now, without waiting.
+    fn try_acquire(&self, len: u64) -> Result<()> {
+        let mut used = self.used.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+        if used.saturating_add(len) > self.limit {
+            return Err(anyhow!("no room for {} bytes within maximum mapped memory", len));
+        }
+        *used += len;
+        Ok(())
+    }
+
+    fn release(&self, len: u64) {
+        if let Ok(mut used) = self.used.lock() {
+            *used = used.saturating_sub(len);
+        }
+        self.released.notify_all();
+    }
+}
+
+/// Bytes charged to the budget until dropped.
+struct Reservation {
+    len: u64,
+    budget: Arc<MappedBudget>,
+    tracking_sender: Sender<JobTracking>,
+}
+
+impl Reservation {
+    fn new(len: u64, budget: Arc<MappedBudget>, tracking_sender: Sender<JobTracking>) -> Reservation {
+        let _ = tracking_sender.send(JobTracking::Mapped(len));
+        Reservation { len, budget, tracking_sender }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget.release(self.len);
+        let _ = self.tracking_sender.send(JobTracking::Unmapped(self.len));
+    }
+}
 
 pub struct Mmap {
     inner: memmap2::Mmap,
-    len: u64,
-    free_sender: Sender<u64>,
+    _reservation: Reservation,
 }
 
 impl Mmap {
-    pub fn new(file: &File, len: u64, free_sender: Sender<u64>) -> Result<Mmap> {
+    /// Maps a file once there is room for it in the budget.
+    pub fn new(file: &File, len: u64, budget: Arc<MappedBudget>, tracking_sender: Sender<JobTracking>) -> Result<Mmap> {
+        budget.acquire(len)?;
+        let reservation = Reservation::new(len, budget, tracking_sender);
+        let inner = unsafe { memmap2::Mmap::map(file)? };
         Ok(Mmap {
-            inner: unsafe { memmap2::Mmap::map(file)? },
-            len,
-            free_sender,
+            inner,
+            _reservation: reservation,
         })
     }
 }
 
-impl AsRef<[u8]> for Mmap {
-    fn as_ref(&self) -> &[u8] {
-        self.inner.as_ref()
+impl BlobSource for Mmap {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        BlobSource::read_at(&self.inner[..], offset, buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(&self.inner)
     }
 }
 
-impl Drop for Mmap {
-    fn drop(&mut self) {
-        let _ = self.free_sender.send(self.len);
+/// Data a module derived from its input, such as a submitted output buffer or decompressed data,
+/// queued as a blob of its own. It counts against the budget until its last job finishes, and
+/// can't be created when the budget has no room for it.
+pub struct Derived {
+    data: Arc<Vec<u8>>,
+    _reservation: Reservation,
+}
+
+impl Derived {
+    pub fn new(data: Arc<Vec<u8>>, budget: Arc<MappedBudget>, tracking_sender: Sender<JobTracking>) -> Result<Derived> {
+        let len = data.len() as u64;
+        budget.try_acquire(len)?;
+        Ok(Derived { data, _reservation: Reservation::new(len, budget, tracking_sender) })
+    }
+
+    /// A copy of `blob` in one piece, charged before the copy is made.
+    pub fn copy(blob: &dyn BlobSource, budget: Arc<MappedBudget>, tracking_sender: Sender<JobTracking>) -> Result<Derived> {
+        let len = blob.len() as u64;
+        budget.try_acquire(len)?;
+        let reservation = Reservation::new(len, budget, tracking_sender);
+        Ok(Derived { data: Arc::new(blob.bytes().into_owned()), _reservation: reservation })
+    }
+}
+
+impl BlobSource for Derived {
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.data.read_at(offset, buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(&self.data)
+    }
+}
+
+/// The blob itself if it is contiguous, otherwise a copy of it in one piece, counted against the
+/// budget for as long as it is held. For consumers, such as hashing and scanning, that need one
+/// slice.
+pub fn contiguous(blob: Blob, budget: Arc<MappedBudget>, tracking_sender: Sender<JobTracking>) -> Result<Blob> {
+    match blob.as_slice() {
+        Some(_) => Ok(blob),
+        None => Ok(Arc::new(Derived::copy(blob.as_ref(), budget, tracking_sender)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpmc::channel;
+    use super::*;
+
+    #[test]
+    fn derived_fails_without_room() {
+        let budget = Arc::new(MappedBudget::new(8));
+        let (tracking_sender, _tracking_receiver) = channel();
+        let first = Derived::new(Arc::new(vec![0; 6]), budget.clone(), tracking_sender.clone()).unwrap();
+        assert!(Derived::new(Arc::new(vec![0; 3]), budget.clone(), tracking_sender.clone()).is_err());
+        assert!(Derived::copy(&vec![0u8; 3], budget.clone(), tracking_sender.clone()).is_err());
+        drop(first);
+        assert!(Derived::new(Arc::new(vec![0; 8]), budget, tracking_sender).is_ok());
     }
 }
@@ -0,0 +1,160 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+use anyhow::{Result, anyhow};
+
+use crate::types::BlobSource;
+
+/// A module's output buffer. It starts in memory and moves to an anonymous temporary file once it
+/// grows past the spill threshold, after which reads and writes go to the file. Clones share the
+/// data, in memory or on disk, and a write to a shared buffer copies it first, so a clone is a
+/// snapshot that later writes don't change.
+#[derive(Clone)]
+pub enum OutputBuffer {
+    Memory(Arc<Vec<u8>>),
+    Spilled {
+        file: Arc<Mutex<File>>,
+        len: usize,
+    },
+}
+
+impl Default for OutputBuffer {
+    fn default() -> OutputBuffer {
+        OutputBuffer::Memory(Default::default())
+    }
+}
+
+impl OutputBuffer {
+    /// A buffer holding `data`, spilled straight away if it is already past the threshold.
+    pub fn new(data: Vec<u8>, spill: usize) -> Result<OutputBuffer> {
+        let mut buffer = OutputBuffer::Memory(Arc::new(data));
+        if buffer.len() > spill {
+            buffer.spill()?;
+        }
+        Ok(buffer)
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        if let OutputBuffer::Memory(data) = self {
+            let mut file = tempfile::tempfile().map_err(|e| anyhow!("unable to create spill file: {}", e))?;
+            file.write_all(data).map_err(|e| anyhow!("unable to write spill file: {}", e))?;
+            *self = OutputBuffer::Spilled { len: data.len(), file: Arc::new(Mutex::new(file)) };
+        }
+        Ok(())
+    }
+
+    /// Writes `data` at `offset`, filling any gap past the current end with zeros.
+    pub fn write_at(&mut self, offset: usize, data: &[u8], spill: usize) -> Result<()> {
+        let end = offset.checked_add(data.len()).ok_or_else(|| anyhow!("write out of range"))?;
+        if end > spill {
+            self.spill()?;
+        }
+        match self {
+            OutputBuffer::Memory(buffer) => {
+                let buffer = Arc::make_mut(buffer);
+                buffer.resize(std::cmp::max(end, buffer.len()), 0);
+                buffer[offset..end].copy_from_slice(data);
+            },
+            OutputBuffer::Spilled { file, len } => {
+                if Arc::get_mut(file).is_none() {
+                    *file = Arc::new(Mutex::new(copy_file(file, *len)?));
+                }
+                let file = Arc::get_mut(file).and_then(|f| f.get_mut().ok()).ok_or_else(|| anyhow!("unable to lock mutex"))?;
+                // Growing the file first zero-fills the gap, even when `data` is empty
+                if end > *len {
+                    file.set_len(end as u64).map_err(|e| anyhow!("unable to grow spill file: {}", e))?;
+                    *len = end;
+                }
+                file.seek(SeekFrom::Start(offset as u64))
+                    .and_then(|_| file.write_all(data))
+                    .map_err(|e| anyhow!("unable to write spill file: {}", e))?;
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Copies the first `len` bytes of a spill file still shared with a snapshot into a new one.
+fn copy_file(file: &Mutex<File>, len: usize) -> Result<File> {
+    let mut source = file.lock().map_err(|_| anyhow!("unable to lock mutex"))?;
+    let mut copy = tempfile::tempfile().map_err(|e| anyhow!("unable to create spill file: {}", e))?;
+    source.seek(SeekFrom::Start(0))
+        .and_then(|_| io::copy(&mut (&mut *source).take(len as u64), &mut copy))
+        .map_err(|e| anyhow!("unable to copy spill file: {}", e))?;
+    Ok(copy)
+}
+
+impl BlobSource for OutputBuffer {
+    fn len(&self) -> usize {
+        match self {
+            OutputBuffer::Memory(buffer) => buffer.len(),
+            OutputBuffer::Spilled { len, .. } => *len,
+        }
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        match self {
+            OutputBuffer::Memory(buffer) => buffer.read_at(offset, buf),
+            OutputBuffer::Spilled { file, len } => {
+                let count = buf.len().min(len.saturating_sub(offset));
+                let Ok(mut file) = file.lock() else {
+                    return 0;
+                };
+                match file.seek(SeekFrom::Start(offset as u64)).and_then(|_| file.read_exact(&mut buf[..count])) {
+                    Ok(()) => count,
+                    Err(_) => 0,
+                }
+            },
+        }
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        match self {
+            OutputBuffer::Memory(buffer) => Some(buffer.as_slice()),
+            OutputBuffer::Spilled { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(buffer: &OutputBuffer) -> Vec<u8> {
+        let mut data = vec![0xff; buffer.len()];
+        assert_eq!(buffer.read_at(0, &mut data), buffer.len());
+        data
+    }
+
+    #[test]
+    fn spills_past_threshold() {
+        let mut buffer = OutputBuffer::new(b"abc".to_vec(), 4).unwrap();
+        assert!(buffer.as_slice().is_some());
+        buffer.write_at(3, b"de", 4).unwrap();
+        assert!(buffer.as_slice().is_none());
+        assert_eq!(contents(&buffer), b"abcde");
+    }
+
+    #[test]
+    fn fills_gaps_with_zeros() {
+        for spill in [usize::MAX, 0] {
+            let mut buffer = OutputBuffer::new(b"ab".to_vec(), spill).unwrap();
+            buffer.write_at(4, b"c", spill).unwrap();
+            assert_eq!(contents(&buffer), b"ab\0\0c");
+            buffer.write_at(8, b"", spill).unwrap();
+            assert_eq!(contents(&buffer), b"ab\0\0c\0\0\0");
+        }
+    }
+
+    #[test]
+    fn clones_are_snapshots() {
+        for spill in [usize::MAX, 0] {
+            let mut buffer = OutputBuffer::new(b"abc".to_vec(), spill).unwrap();
+            let snapshot = buffer.clone();
+            buffer.write_at(1, b"x", spill).unwrap();
+            buffer.write_at(5, b"", spill).unwrap();
+            assert_eq!(contents(&buffer), b"axc\0\0");
+            assert_eq!(contents(&snapshot), b"abc");
+        }
+    }
+}
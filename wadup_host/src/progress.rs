@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::job::{JobInfo, JobResult};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const SLOWEST_COUNT: usize = 3;
+
+pub fn format_bytes(value: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = value as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[derive(Default)]
+struct InputProgress {
+    dispatched: bool,
+    outstanding: usize,
+}
+
+pub struct Progress {
+    enabled: bool,
+    mapped_limit: u64,
+    mapped: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+    lines_drawn: usize,
+    jobs: HashMap<Uuid, JobInfo>,
+    running: HashMap<Uuid, Instant>,
+    inputs: HashMap<PathBuf, InputProgress>,
+    files_completed: usize,
+    derived_outstanding: usize,
+    jobs_completed: u64,
+    jobs_filtered: u64,
+    allow_listed: u64,
+    deny_listed: u64,
+}
+
+impl Progress {
+    pub fn new(enabled: bool, mapped_limit: u64) -> Progress {
+        Progress {
+            enabled: enabled && std::io::stdout().is_terminal(),
+            mapped_limit,
+            mapped: 0,
+            started: Instant::now(),
+            last_draw: None,
+            lines_drawn: 0,
+            jobs: HashMap::new(),
+            running: HashMap::new(),
+            inputs: HashMap::new(),
+            files_completed: 0,
+            derived_outstanding: 0,
+            jobs_completed: 0,
+            jobs_filtered: 0,
+            allow_listed: 0,
+            deny_listed: 0,
+        }
+    }
+
+    pub fn input_queued(&mut self, file_path: &Path) {
+        self.inputs.entry(file_path.to_owned()).or_default();
+    }
+
+    pub fn input_dispatched(&mut self, file_path: &Path) {
+        let input = self.inputs.entry(file_path.to_owned()).or_default();
+        input.dispatched = true;
+        if input.outstanding == 0 {
+            self.files_completed += 1;
+        }
+    }
+
+    pub fn job_info(&mut self, info: &JobInfo) {
+        match &info.file_path {
+            Some(file_path) => self.inputs.entry(file_path.clone()).or_default().outstanding += 1,
+            None => self.derived_outstanding += 1,
+        }
+        self.jobs.insert(info.id, info.clone());
+    }
+
+    pub fn job_started(&mut self, id: Uuid) {
+        self.running.insert(id, Instant::now());
+    }
+
+    pub fn job_result(&mut self, result: &JobResult) {
+        self.running.remove(&result.id);
+        let Some(info) = self.jobs.remove(&result.id) else {
+            return;
+        };
+        self.jobs_completed += 1;
+        if result.filtered {
+            self.jobs_filtered += 1;
+        }
+        match info.file_path.and_then(|file_path| self.inputs.get_mut(&file_path)) {
+            Some(input) => {
+                input.outstanding -= 1;
+                if input.dispatched && input.outstanding == 0 {
+                    self.files_completed += 1;
+                }
+            },
+            None => self.derived_outstanding -= 1,
+        }
+    }
+
+    pub fn allow_listed(&mut self) {
+        self.allow_listed += 1;
+    }
+
+    pub fn deny_listed(&mut self) {
+        self.deny_listed += 1;
+    }
+
+    pub fn mapped(&mut self, len: u64) {
+        self.mapped += len;
+    }
+
+    pub fn unmapped(&mut self, len: u64) {
+        self.mapped = self.mapped.saturating_sub(len);
+    }
+
+    /// Prints a line above the progress view, redrawing it afterwards if it is due.
+    pub fn println(&mut self, line: &str) {
+        self.clear();
+        println!("{}", line);
+        self.tick();
+    }
+
+    pub fn tick(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let due = self.last_draw.map(|t| t.elapsed() >= REDRAW_INTERVAL).unwrap_or(true);
+        if due || self.lines_drawn == 0 {
+            self.draw();
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if self.enabled {
+            self.draw();
+        }
+        if self.allow_listed > 0 || self.deny_listed > 0 {
+            println!("SUMMARY: allow-listed blobs skipped: {} | deny-listed blobs flagged: {}", self.allow_listed, self.deny_listed);
+        }
+    }
+
+    fn clear(&mut self) {
+        if self.lines_drawn > 0 {
+            print!("\x1b[{}F\x1b[J", self.lines_drawn);
+            self.lines_drawn = 0;
+        }
+    }
+
+    fn draw(&mut self) {
+        self.clear();
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { self.jobs_completed as f64 / elapsed } else { 0.0 };
+
+        let mut lines = vec![format!(
+            "files: {}/{} completed | derived jobs outstanding: {} | {:.1} jobs/s | filtered: {} | allow-listed: {} | deny-listed: {} | mapped: {} / {}",
+            self.files_completed,
+            self.inputs.len(),
+            self.derived_outstanding,
+            rate,
+            self.jobs_filtered,
+            self.allow_listed,
+            self.deny_listed,
+            format_bytes(self.mapped),
+            format_bytes(self.mapped_limit),
+        )];
+
+        let mut running = self.running.iter().collect::<Vec<_>>();
+        running.sort_by_key(|(_, started)| *started);
+        for (id, started) in running.into_iter().take(SLOWEST_COUNT) {
+            let (module_name, file_path) = self.jobs.get(id)
+                .map(|info| (info.module_name.as_str(), info.file_path.as_ref()))
+                .unwrap_or(("?", None));
+            lines.push(match file_path {
+                Some(file_path) => format!("  {:>6.1}s {} {:?}", started.elapsed().as_secs_f64(), module_name, file_path),
+                None => format!("  {:>6.1}s {} (derived)", started.elapsed().as_secs_f64(), module_name),
+            });
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        for line in &lines {
+            let _ = writeln!(stdout, "{}", line);
+        }
+        let _ = stdout.flush();
+        self.lines_drawn = lines.len();
+        self.last_draw = Some(Instant::now());
+    }
+}
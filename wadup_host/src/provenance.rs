@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::decompress::Compression;
+use crate::filetype::FileType;
+use crate::transform::Transform;
+use crate::yara::RuleMatch;
+
+/// Where a blob came from: the root input file and the chain of carves that produced it.
+#[derive(Clone, Debug)]
+pub struct Provenance {
+    pub file_path: PathBuf,
+    pub file_name: Option<String>,
+    pub offset: u64,
+    pub depth: u32,
+    pub parent_module: Option<String>,
+    pub hint: Option<String>,
+    pub lineage: Lineage,
+    pub tags: Tags,
+    /// The (offset, length) fragments of the parent a concatenated blob was stitched from
+    pub ranges: Vec<(u64, u64)>,
+    /// The decoding a transformed blob is read through
+    pub transform: Option<Transform>,
+    /// Identified by the host before the blob is routed
+    pub file_type: Option<FileType>,
+    /// YARA rules that matched the blob
+    pub yara: Vec<RuleMatch>,
+    /// The YARA rules, as `namespace.rule`, the host carved the blob and its ancestors at matches of
+    pub yara_carved_by: Vec<String>,
+}
+
+/// How a blob was derived from its parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lineage {
+    Root,
+    Carved,
+    Submitted,
+    Decompressed(Compression),
+    Concatenated,
+    Transformed,
+}
+
+impl fmt::Display for Lineage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lineage::Root => write!(f, "root"),
+            Lineage::Carved => write!(f, "carved from"),
+            Lineage::Submitted => write!(f, "submitted from"),
+            Lineage::Decompressed(_) => write!(f, "decompressed from"),
+            Lineage::Concatenated => write!(f, "concatenated from"),
+            Lineage::Transformed => write!(f, "transformed from"),
+        }
+    }
+}
+
+/// Key/value labels on a blob. Every job running on the blob shares them, and blobs derived from
+/// it start with a copy of the tags set so far.
+#[derive(Default)]
+pub struct Tags(Mutex<BTreeMap<String, String>>);
+
+impl Tags {
+    pub fn set(&self, key: String, value: String) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).insert(key, value);
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, String> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Clone for Tags {
+    fn clone(&self) -> Tags {
+        Tags(Mutex::new(self.snapshot()))
+    }
+}
+
+impl fmt::Debug for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.snapshot().fmt(f)
+    }
+}
+
+impl Provenance {
+    pub fn root(file_path: &Path) -> Provenance {
+        Provenance {
+            file_path: file_path.to_owned(),
+            file_name: file_path.file_name().map(|n| n.to_string_lossy().into_owned()),
+            offset: 0,
+            depth: 0,
+            parent_module: None,
+            hint: None,
+            lineage: Lineage::Root,
+            tags: Tags::default(),
+            ranges: Vec::new(),
+            transform: None,
+            file_type: None,
+            yara: Vec::new(),
+            yara_carved_by: Vec::new(),
+        }
+    }
+
+    pub fn child(&self, module_name: &str, offset: u64) -> Provenance {
+        Provenance {
+            file_path: self.file_path.clone(),
+            file_name: None,
+            offset,
+            depth: self.depth + 1,
+            parent_module: Some(module_name.to_owned()),
+            hint: None,
+            lineage: Lineage::Carved,
+            tags: self.tags.clone(),
+            ranges: Vec::new(),
+            transform: None,
+            file_type: None,
+            yara: Vec::new(),
+            yara_carved_by: self.yara_carved_by.clone(),
+        }
+    }
+
+    /// A blob carved by the host rather than by a module, e.g. at a YARA match.
+    pub fn host_child(&self, offset: u64) -> Provenance {
+        Provenance {
+            parent_module: None,
+            ..self.child("", offset)
+        }
+    }
+
+    /// The fields visible to modules through `wadup_input_info_read`.
+    pub fn info(&self) -> Vec<(&'static str, String)> {
+        let mut info = vec![
+            ("file_path", self.file_path.to_string_lossy().into_owned()),
+            ("offset", self.offset.to_string()),
+            ("depth", self.depth.to_string()),
+            ("lineage", self.lineage.to_string()),
+        ];
+        if let Lineage::Decompressed(compression) = self.lineage {
+            info.push(("compression", compression.name().to_owned()));
+        }
+        if !self.ranges.is_empty() {
+            let ranges = self.ranges.iter().map(|(offset, length)| format!("{}:{}", offset, length)).collect::<Vec<_>>();
+            info.push(("ranges", ranges.join(" ")));
+        }
+        if let Some(transform) = &self.transform {
+            info.push(("transform", transform.to_string()));
+        }
+        if let Some(file_type) = &self.file_type {
+            info.push(("file_type", file_type.name.to_owned()));
+            info.push(("mime", file_type.mime.to_owned()));
+        }
+        if let Some(parent_module) = &self.parent_module {
+            info.push(("parent_module", parent_module.clone()));
+        }
+        if let Some(hint) = &self.hint {
+            info.push(("hint", hint.clone()));
+        }
+        if let Some(file_name) = &self.file_name {
+            info.push(("file_name", file_name.clone()));
+        }
+        info
+    }
+
+    pub fn extension(&self) -> Option<String> {
+        let file_name = self.file_name.as_ref()?;
+        Path::new(file_name).extension().map(|e| e.to_string_lossy().to_ascii_lowercase())
+    }
+}
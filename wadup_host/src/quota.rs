@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::Result;
+
+use crate::config::Quotas;
+
+/// The error a host function fails with when a job goes over one of its quotas. It ends the job
+/// like any other error, but is reported as a quota rather than a fault in the module.
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    pub quota: &'static str,
+    pub limit: u64,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "quota exceeded: {} limited to {}", self.quota, self.limit)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+fn check(quota: &'static str, used: u64, limit: u64) -> Result<()> {
+    if used > limit {
+        Err(QuotaExceeded { quota, limit }.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// What a job has produced so far, counted against the configured quotas.
+#[derive(Default)]
+pub struct Usage {
+    output_bytes: u64,
+    rows: HashMap<String, u64>,
+    carves: u64,
+}
+
+impl Usage {
+    /// Charges `grown` more bytes of output, failing without charging if it would exceed the quota.
+    pub fn output_bytes(&mut self, quotas: &Quotas, grown: u64) -> Result<()> {
+        let used = self.output_bytes.saturating_add(grown);
+        check("output bytes", used, quotas.output_bytes.0)?;
+        self.output_bytes = used;
+        Ok(())
+    }
+
+    /// How many more bytes of output the quota allows.
+    pub fn output_bytes_remaining(&self, quotas: &Quotas) -> u64 {
+        quotas.output_bytes.0.saturating_sub(self.output_bytes)
+    }
+
+    /// Checks that `count` output buffers are within the quota.
+    pub fn output_buffers(&self, quotas: &Quotas, count: usize) -> Result<()> {
+        check("output buffers", count as u64, quotas.output_buffers as u64)
+    }
+
+    pub fn row(&mut self, quotas: &Quotas, schema: &str) -> Result<()> {
+        let rows = self.rows.entry(schema.to_owned()).or_default();
+        check("rows per schema", *rows + 1, quotas.rows)?;
+        *rows += 1;
+        Ok(())
+    }
+
+    pub fn carve(&mut self, quotas: &Quotas) -> Result<()> {
+        check("carves", self.carves + 1, quotas.carves)?;
+        self.carves += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ByteSize;
+use crate::provenance::Provenance;
+use crate::signature::Signature;
+
+/// Selects the blobs a module runs on. Every criterion that is given must match; list criteria
+/// match if any entry matches. A module with several routes runs if any of them matches, and a
+/// module with no routes runs on everything.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Route {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub magic: Vec<Signature>,
+    pub min_size: Option<ByteSize>,
+    pub max_size: Option<ByteSize>,
+    pub min_depth: Option<u32>,
+    pub max_depth: Option<u32>,
+    /// File types identified by the host, by name (e.g. `"pe"`) or MIME type
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_types: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<String>,
+    /// Tags the blob must carry with exactly these values, usually inherited from its parent
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// YARA rules of which at least one must have matched the blob
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub yara: Vec<String>,
+}
+
+impl Route {
+    pub fn matches(&self, data: &[u8], provenance: &Provenance) -> bool {
+        let size = data.len() as u64;
+
+        if !self.extensions.is_empty() {
+            let extension = provenance.extension();
+            let matched = self.extensions.iter().any(|e| {
+                Some(e.trim_start_matches('.').to_ascii_lowercase()) == extension
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        if !self.magic.is_empty() && !self.magic.iter().any(|m| m.matches(data)) {
+            return false;
+        }
+
+        if self.min_size.map(|min| size < min.0).unwrap_or(false) || self.max_size.map(|max| size > max.0).unwrap_or(false) {
+            return false;
+        }
+
+        if self.min_depth.map(|min| provenance.depth < min).unwrap_or(false) || self.max_depth.map(|max| provenance.depth > max).unwrap_or(false) {
+            return false;
+        }
+
+        if !self.file_types.is_empty() {
+            let matched = provenance.file_type
+                .is_some_and(|file_type| self.file_types.iter().any(|t| file_type.is(t)));
+            if !matched {
+                return false;
+            }
+        }
+
+        if !self.parents.is_empty() {
+            let parent = provenance.parent_module.as_deref();
+            if !self.parents.iter().any(|p| Some(p.as_str()) == parent) {
+                return false;
+            }
+        }
+
+        if !self.hints.is_empty() {
+            let hint = provenance.hint.as_deref();
+            if !self.hints.iter().any(|h| Some(h.as_str()) == hint) {
+                return false;
+            }
+        }
+
+        if !self.tags.is_empty() {
+            let tags = provenance.tags.snapshot();
+            if !self.tags.iter().all(|(k, v)| tags.get(k) == Some(v)) {
+                return false;
+            }
+        }
+
+        if !self.yara.is_empty() && !provenance.yara.iter().any(|m| self.yara.contains(&m.rule)) {
+            return false;
+        }
+
+        true
+    }
+}
+
+pub fn matches_any(routes: &[Route], data: &[u8], provenance: &Provenance) -> bool {
+    routes.is_empty() || routes.iter().any(|r| r.matches(data, provenance))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::filetype::identify;
+    use super::*;
+
+    fn route(text: &str) -> Route {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn no_routes_match_everything() {
+        let provenance = Provenance::root(Path::new("input/a.bin"));
+        assert!(matches_any(&[], b"anything", &provenance));
+        assert!(route("").matches(b"anything", &provenance));
+    }
+
+    #[test]
+    fn matches_extension_ignoring_case_and_dot() {
+        let route = route("extensions = [\".ZIP\", \"jar\"]");
+        assert!(route.matches(b"", &Provenance::root(Path::new("input/a.zip"))));
+        assert!(route.matches(b"", &Provenance::root(Path::new("input/b.JAR"))));
+        assert!(!route.matches(b"", &Provenance::root(Path::new("input/c.tar"))));
+        assert!(!route.matches(b"", &Provenance::root(Path::new("input/zip"))));
+    }
+
+    #[test]
+    fn matches_size_and_depth_bounds() {
+        let route = route("min_size = 2\nmax_size = \"1KiB\"\nmin_depth = 1\nmax_depth = 2");
+        let root = Provenance::root(Path::new("input/a.bin"));
+        let child = root.child("module1.wasm", 0);
+        let grandchild = child.child("module1.wasm", 0);
+        assert!(!route.matches(b"abc", &root));
+        assert!(route.matches(b"abc", &child));
+        assert!(route.matches(&[0; 1024], &grandchild));
+        assert!(!route.matches(&[0; 1025], &grandchild));
+        assert!(!route.matches(b"a", &child));
+        assert!(!route.matches(b"abc", &grandchild.child("module1.wasm", 0)));
+    }
+
+    #[test]
+    fn matches_parent_hint_and_tags() {
+        let route = route("parents = [\"module1.wasm\"]\nhints = [\"zip\"]\ntags = { family = \"x\" }");
+        let root = Provenance::root(Path::new("input/a.bin"));
+        root.tags.set("family".to_owned(), "x".to_owned());
+        let mut child = root.child("module1.wasm", 0);
+        assert!(!route.matches(b"", &child));
+        child.hint = Some("zip".to_owned());
+        assert!(route.matches(b"", &child));
+        child.tags.set("family".to_owned(), "y".to_owned());
+        assert!(!route.matches(b"", &child));
+        let mut other = root.child("module2.wasm", 0);
+        other.hint = Some("zip".to_owned());
+        assert!(!route.matches(b"", &other));
+    }
+
+    #[test]
+    fn matches_magic_and_file_type() {
+        let data = b"PK\x03\x04rest";
+        let mut provenance = Provenance::root(Path::new("input/a.bin"));
+        provenance.file_type = Some(identify(data));
+        assert!(route("magic = [{ bytes = \"504b0304\" }]").matches(data, &provenance));
+        assert!(!route("magic = [{ offset = 1, bytes = \"504b\" }]").matches(data, &provenance));
+        assert!(route(&format!("file_types = [{:?}]", provenance.file_type.unwrap().name)).matches(data, &provenance));
+        assert!(route(&format!("file_types = [{:?}]", provenance.file_type.unwrap().mime)).matches(data, &provenance));
+        assert!(!route("file_types = [\"pe\"]").matches(data, &provenance));
+    }
+
+    #[test]
+    fn matches_any_route() {
+        let routes = [route("extensions = [\"zip\"]"), route("min_size = 4")];
+        assert!(matches_any(&routes, b"ab", &Provenance::root(Path::new("input/a.zip"))));
+        assert!(matches_any(&routes, b"abcd", &Provenance::root(Path::new("input/a.bin"))));
+        assert!(!matches_any(&routes, b"ab", &Provenance::root(Path::new("input/a.bin"))));
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::mpmc::Sender;
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value, json};
+
+use crate::encoding::encode_pairs;
+use crate::job::JobTracking;
+use crate::types::DataValue;
+
+#[derive(Debug)]
+pub struct Row {
+    pub schema: String,
+    pub values: Vec<(String, DataValue)>,
+    /// Tags on the blob the row was emitted for, at the time it was emitted
+    pub tags: BTreeMap<String, String>,
+}
+
+impl Row {
+    /// The row as passed to modules: a pair per column, each value prefixed with its type.
+    pub fn encode(&self) -> Vec<u8> {
+        encode_pairs(self.values.iter().map(|(column, value)| (column, value.encode())))
+    }
+
+    /// The row as a line of the results file.
+    pub fn to_json(&self) -> Value {
+        let values = self.values.iter()
+            .map(|(column, value)| (column.clone(), value.to_json()))
+            .collect::<Map<_, _>>();
+        json!({ "schema": self.schema, "values": values, "tags": self.tags })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Row> {
+        let schema = value["schema"].as_str().ok_or_else(|| anyhow!("row has no schema"))?;
+        let values = value["values"].as_object().ok_or_else(|| anyhow!("row has no values"))?;
+        let tags = value["tags"].as_object().into_iter().flatten()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_owned())))
+            .collect();
+        Ok(Row {
+            schema: schema.to_owned(),
+            values: values.iter().map(|(column, value)| (column.clone(), DataValue::from_json(value))).collect(),
+            tags,
+        })
+    }
+
+    pub fn get(&self, column: &str) -> Option<&DataValue> {
+        self.values.iter().find(|(c, _)| c == column).map(|(_, v)| v)
+    }
+}
+
+/// Reads back the rows appended to a results file.
+pub fn read_results(path: &Path) -> Result<Vec<Row>> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("unable to read results {:?}: {}", path, e))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let value = serde_json::from_str(line).map_err(|e| anyhow!("results {:?} line {}: {}", path, i + 1, e))?;
+            Row::from_json(&value).map_err(|e| anyhow!("results {:?} line {}: {}", path, i + 1, e))
+        })
+        .collect()
+}
+
+/// Receives every row emitted during a run. Rows are only kept in memory when a reduce module
+/// needs to read them back, and are appended to the results file when one is configured.
+pub struct Rows {
+    retain: bool,
+    rows: Mutex<Vec<Arc<Row>>>,
+    results: Option<Mutex<LineWriter<File>>>,
+}
+
+impl Rows {
+    pub fn new(retain: bool, results: Option<&Path>) -> Result<Rows> {
+        let results = results
+            .map(|path| {
+                OpenOptions::new().create(true).append(true).open(path)
+                    .map_err(|e| anyhow!("unable to open results {:?}: {}", path, e))
+            })
+            .transpose()?;
+        Ok(Rows {
+            retain,
+            rows: Mutex::new(Vec::new()),
+            results: results.map(|file| Mutex::new(LineWriter::new(file))),
+        })
+    }
+
+    /// Records a row, printing it through the tracker so it doesn't clash with the progress view.
+    pub fn emit(&self, row: Row, tracking_sender: &Sender<JobTracking>) -> Result<()> {
+        let lines = row.values.iter()
+            .map(|(column, value)| format!("DATA: {} {} {:?}", row.schema, column, value))
+            .chain(row.tags.iter().map(|(key, value)| format!("TAG: {} {} {:?}", row.schema, key, value)))
+            .collect::<Vec<_>>();
+        if !lines.is_empty() {
+            let _ = tracking_sender.send(JobTracking::Output(lines.join("\n")));
+        }
+        if let Some(results) = &self.results {
+            let mut results = results.lock().map_err(|_| anyhow!("rows unable to lock mutex"))?;
+            writeln!(results, "{}", row.to_json()).map_err(|e| anyhow!("unable to write results: {}", e))?;
+        }
+        if self.retain {
+            self.rows.lock().map_err(|_| anyhow!("rows unable to lock mutex"))?.push(Arc::new(row));
+        }
+        Ok(())
+    }
+
+    /// A snapshot of the rows emitted so far with the given schema name.
+    pub fn query(&self, schema: &str) -> Result<Vec<Arc<Row>>> {
+        let rows = self.rows.lock().map_err(|_| anyhow!("rows unable to lock mutex"))?;
+        Ok(rows.iter().filter(|r| r.schema == schema).cloned().collect())
+    }
+}
+
+/// A module's position in the result of `wadup_rows_query`.
+pub struct RowCursor {
+    pub rows: Vec<Arc<Row>>,
+    pub position: Option<usize>,
+    pub current: Vec<u8>,
+}
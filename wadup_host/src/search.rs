@@ -0,0 +1,121 @@
+use aho_corasick::{AhoCorasick, Input};
+use aho_corasick::automaton::OverlappingState;
+use anyhow::{Result, anyhow};
+use regex::bytes::Regex;
+
+use crate::types::Blob;
+
+/// Size of a match as written by `wadup_search_next`: pattern index (u32), start and end (u64),
+/// all little endian.
+pub const MATCH_SIZE: usize = 20;
+
+enum Matcher {
+    /// Every occurrence of every pattern, including overlapping ones
+    Patterns(AhoCorasick, OverlappingState),
+    /// Successive non-overlapping matches, resuming at the position
+    Regex(Regex, usize),
+}
+
+/// A search over a range of a blob that hands out matches a batch at a time.
+pub struct Search {
+    data: Blob,
+    start: usize,
+    end: usize,
+    matcher: Matcher,
+    /// How far into the range the search has looked
+    scanned: usize,
+    done: bool,
+}
+
+impl Search {
+    pub fn patterns(data: Blob, start: usize, end: usize, patterns: &[Vec<u8>]) -> Result<Search> {
+        let automaton = AhoCorasick::new(patterns).map_err(|e| anyhow!("invalid patterns: {}", e))?;
+        Search::new(data, start, end, Matcher::Patterns(automaton, OverlappingState::start()))
+    }
+
+    pub fn regex(data: Blob, start: usize, end: usize, pattern: &str) -> Result<Search> {
+        let regex = Regex::new(pattern).map_err(|e| anyhow!("invalid regex: {}", e))?;
+        Search::new(data, start, end, Matcher::Regex(regex, start))
+    }
+
+    fn new(data: Blob, start: usize, end: usize, matcher: Matcher) -> Result<Search> {
+        if start > end || end > data.len() {
+            return Err(anyhow!("search range out of bounds"));
+        }
+        // Matching needs one slice, which the caller copies a source that isn't contiguous into
+        if data.as_slice().is_none() {
+            return Err(anyhow!("search needs a contiguous blob"));
+        }
+        Ok(Search { data, start, end, matcher, scanned: start, done: false })
+    }
+
+    /// Encodes up to `max` further matches, with offsets relative to the start of the blob, and
+    /// returns them with the number of bytes scanned to find them.
+    pub fn next_batch(&mut self, max: usize) -> (Vec<u8>, usize) {
+        let data = self.data.as_slice().unwrap_or_default();
+        let scanned = self.scanned;
+        let mut batch = Vec::new();
+        while !self.done && batch.len() < max.saturating_mul(MATCH_SIZE) {
+            let found = match &mut self.matcher {
+                Matcher::Patterns(automaton, state) => {
+                    let input = Input::new(data).range(self.start..self.end);
+                    automaton.find_overlapping(input, state);
+                    state.get_match().map(|m| (m.pattern().as_u32(), m.start(), m.end()))
+                },
+                Matcher::Regex(regex, position) => {
+                    let found = (*position <= self.end).then(|| regex.find_at(&data[..self.end], *position)).flatten();
+                    if let Some(m) = &found {
+                        // Step past empty matches so the search always moves forward
+                        *position = if m.is_empty() { m.end() + 1 } else { m.end() };
+                    }
+                    found.map(|m| (0, m.start(), m.end()))
+                },
+            };
+            match found {
+                Some((pattern, start, end)) => {
+                    batch.extend_from_slice(&pattern.to_le_bytes());
+                    batch.extend_from_slice(&(start as u64).to_le_bytes());
+                    batch.extend_from_slice(&(end as u64).to_le_bytes());
+                    self.scanned = self.scanned.max(end);
+                },
+                None => {
+                    self.done = true;
+                    self.scanned = self.end;
+                },
+            }
+        }
+        (batch, self.scanned - scanned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+
+    #[test]
+    fn batches_matches_and_counts_bytes_scanned() {
+        let data: Blob = Arc::new(b"xaxxaxxxa".to_vec());
+        let mut search = Search::patterns(data, 0, 9, &[b"a".to_vec()]).unwrap();
+        let (batch, scanned) = search.next_batch(2);
+        assert_eq!(batch.len(), 2 * MATCH_SIZE);
+        assert_eq!(scanned, 5);
+        assert_eq!(&batch[MATCH_SIZE + 4..MATCH_SIZE + 12], &4u64.to_le_bytes());
+        let (batch, scanned) = search.next_batch(usize::MAX);
+        assert_eq!(batch.len(), MATCH_SIZE);
+        assert_eq!(scanned, 4);
+        assert_eq!(search.next_batch(1), (Vec::new(), 0));
+    }
+
+    #[test]
+    fn regex_steps_past_empty_matches() {
+        let data: Blob = Arc::new(b"abc".to_vec());
+        let mut search = Search::regex(data, 0, 3, "").unwrap();
+        let (batch, scanned) = search.next_batch(0);
+        assert!(batch.is_empty());
+        assert_eq!(scanned, 0);
+        let (batch, scanned) = search.next_batch(10);
+        assert_eq!(batch.len(), 4 * MATCH_SIZE);
+        assert_eq!(scanned, 3);
+    }
+}
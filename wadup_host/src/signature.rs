@@ -0,0 +1,142 @@
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use wasmparser::{Parser, Payload};
+
+use crate::config::HexBytes;
+use crate::types::BlobSource;
+
+/// Custom section a module can embed to declare its signatures, one `offset:bytes[/mask]` per line.
+pub const SIGNATURE_SECTION: &str = "wadup_signatures";
+
+/// Bytes expected at an offset of the blob. Where a mask is given only the bits set in the mask
+/// are compared.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Signature {
+    #[serde(default)]
+    pub offset: u64,
+    pub bytes: HexBytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<HexBytes>,
+}
+
+impl Signature {
+    pub fn matches(&self, data: &(impl BlobSource + ?Sized)) -> bool {
+        let window = usize::try_from(self.offset).ok()
+            .and_then(|start| data.range(start, start.checked_add(self.bytes.0.len())?));
+        let Some(window) = window else {
+            return false;
+        };
+        match &self.mask {
+            Some(mask) => window.iter().zip(&self.bytes.0).enumerate().all(|(i, (actual, expected))| {
+                let mask = mask.0.get(i).copied().unwrap_or(0xff);
+                actual & mask == expected & mask
+            }),
+            None => window == self.bytes.0.as_slice(),
+        }
+    }
+}
+
+impl FromStr for Signature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Signature> {
+        let (offset, pattern) = s.split_once(':').ok_or_else(|| anyhow!("signature {:?} is not offset:bytes[/mask]", s))?;
+        let offset = offset.trim().parse::<u64>().map_err(|_| anyhow!("signature {:?} has an invalid offset", s))?;
+        let (bytes, mask) = match pattern.split_once('/') {
+            Some((bytes, mask)) => (bytes.parse()?, Some(mask.parse()?)),
+            None => (pattern.parse()?, None),
+        };
+        Ok(Signature { offset, bytes, mask })
+    }
+}
+
+pub fn matches_any(signatures: &[Signature], data: &(impl BlobSource + ?Sized)) -> bool {
+    signatures.is_empty() || signatures.iter().any(|s| s.matches(data))
+}
+
+/// Reads the signatures embedded in a compiled module's `wadup_signatures` custom sections.
+pub fn read_signature_section(wasm: &[u8]) -> Result<Vec<Signature>> {
+    // Text format modules are accepted by wasmtime but carry no custom sections
+    if !wasm.starts_with(b"\0asm") {
+        return Ok(Vec::new());
+    }
+
+    let mut signatures = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CustomSection(section) = payload? {
+            if section.name() != SIGNATURE_SECTION {
+                continue;
+            }
+            let text = std::str::from_utf8(section.data()).map_err(|_| anyhow!("{} section is not UTF8", SIGNATURE_SECTION))?;
+            for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                signatures.push(line.parse()?);
+            }
+        }
+    }
+    Ok(signatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module with no content but one custom section.
+    fn module_with_section(name: &str, data: &str) -> Vec<u8> {
+        let mut payload = vec![name.len() as u8];
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(data.as_bytes());
+        let mut wasm = b"\0asm\x01\0\0\0\0".to_vec();
+        wasm.push(payload.len() as u8);
+        wasm.extend(payload);
+        wasm
+    }
+
+    #[test]
+    fn matches_bytes_at_offset() {
+        let signature: Signature = "2:cafe".parse().unwrap();
+        assert!(signature.matches(b"\0\0\xca\xfe\0".as_slice()));
+        assert!(!signature.matches(b"\xca\xfe\0\0".as_slice()));
+        assert!(!signature.matches(b"\0\0\xca".as_slice()));
+    }
+
+    #[test]
+    fn compares_only_masked_bits() {
+        let signature: Signature = "0:4d5a00/ffff00".parse().unwrap();
+        assert!(signature.matches(b"MZ\x90".as_slice()));
+        assert!(!signature.matches(b"MX\x90".as_slice()));
+        // Bytes past the end of a short mask are compared in full
+        let signature: Signature = "0:4d5a/f0".parse().unwrap();
+        assert!(signature.matches(b"AZ".as_slice()));
+        assert!(!signature.matches(b"AY".as_slice()));
+    }
+
+    #[test]
+    fn rejects_invalid_signatures() {
+        assert!("cafe".parse::<Signature>().is_err());
+        assert!("x:cafe".parse::<Signature>().is_err());
+        assert!("0:caf".parse::<Signature>().is_err());
+        assert!("0:cafe/zz".parse::<Signature>().is_err());
+    }
+
+    #[test]
+    fn no_signatures_match_everything() {
+        assert!(matches_any(&[], b"".as_slice()));
+        let signatures = ["0:00".parse().unwrap(), "0:ff".parse().unwrap()];
+        assert!(matches_any(&signatures, b"\xff".as_slice()));
+        assert!(!matches_any(&signatures, b"\x01".as_slice()));
+    }
+
+    #[test]
+    fn reads_signature_section() {
+        let wasm = module_with_section(SIGNATURE_SECTION, "0:504b0304\n\n 4:ff/0f \n");
+        let signatures = read_signature_section(&wasm).unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[1].offset, 4);
+        assert_eq!(signatures[1].mask, Some(HexBytes(vec![0x0f])));
+        assert!(read_signature_section(&module_with_section("other", "junk")).unwrap().is_empty());
+        assert!(read_signature_section(b"(module)").unwrap().is_empty());
+        assert!(read_signature_section(&module_with_section(SIGNATURE_SECTION, "junk")).is_err());
+    }
+}
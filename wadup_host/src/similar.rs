@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+use crate::fuzzy::{TLSH_NULL, ssdeep_similarity, tlsh_distance};
+use crate::hash::{HASHES_SCHEMA, HashAlgorithm};
+use crate::rows::read_results;
+use crate::types::DataValue;
+
+/// A blob from the results file and its digest.
+struct Digest {
+    file_path: String,
+    offset: i64,
+    depth: i64,
+    digest: String,
+}
+
+/// Whether two digests are close enough to be clustered together.
+fn similar(algorithm: HashAlgorithm, threshold: u32, a: &str, b: &str) -> bool {
+    match algorithm {
+        HashAlgorithm::Ssdeep => ssdeep_similarity(a, b) >= threshold,
+        HashAlgorithm::Tlsh => tlsh_distance(a, b).is_some_and(|d| d <= threshold),
+        _ => a == b,
+    }
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    // Path compression keeps later lookups short
+    let mut i = i;
+    while parents[i] != root {
+        let next = parents[i];
+        parents[i] = root;
+        i = next;
+    }
+    root
+}
+
+/// Clusters the blobs in a results file whose fuzzy hashes are within the threshold of each other,
+/// directly or through a chain of similar blobs, and prints every cluster of two or more.
+pub fn similar_command(results: &Path, algorithm: HashAlgorithm, threshold: Option<u32>) -> Result<()> {
+    let threshold = match algorithm {
+        HashAlgorithm::Ssdeep => threshold.unwrap_or(50),
+        HashAlgorithm::Tlsh => threshold.unwrap_or(100),
+        _ => return Err(anyhow!("{} is not a fuzzy hash", algorithm.name())),
+    };
+
+    let digests = read_results(results)?.into_iter()
+        .filter(|row| row.schema == HASHES_SCHEMA)
+        .filter_map(|row| {
+            let Some(DataValue::StringValue(digest)) = row.get(algorithm.name()) else {
+                return None;
+            };
+            if digest == TLSH_NULL {
+                return None;
+            }
+            let file_path = match row.get("file_path") {
+                Some(DataValue::StringValue(file_path)) => file_path.clone(),
+                _ => String::new(),
+            };
+            let integer = |column| match row.get(column) {
+                Some(DataValue::Int64Value(value)) => *value,
+                _ => 0,
+            };
+            Some(Digest { file_path, offset: integer("offset"), depth: integer("depth"), digest: digest.clone() })
+        })
+        .collect::<Vec<_>>();
+
+    // ssdeep digests can only be compared when their block sizes are equal or one is double the
+    // other, so each block size is compared only with itself and the next; other digests form a
+    // single group
+    let mut groups = BTreeMap::<u64, Vec<usize>>::new();
+    for (i, digest) in digests.iter().enumerate() {
+        let block_size = match algorithm {
+            HashAlgorithm::Ssdeep => digest.digest.split(':').next().and_then(|s| s.parse().ok()).unwrap_or(0),
+            _ => 0,
+        };
+        groups.entry(block_size).or_default().push(i);
+    }
+
+    let mut parents = (0..digests.len()).collect::<Vec<_>>();
+    let mut join = |i: usize, j: usize| {
+        if similar(algorithm, threshold, &digests[i].digest, &digests[j].digest) {
+            let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+            parents[a.max(b)] = a.min(b);
+        }
+    };
+    for (block_size, group) in &groups {
+        for (k, i) in group.iter().enumerate() {
+            for j in &group[k + 1..] {
+                join(*i, *j);
+            }
+        }
+        let doubled = match algorithm {
+            HashAlgorithm::Ssdeep if *block_size > 0 => block_size.checked_mul(2).and_then(|size| groups.get(&size)),
+            _ => None,
+        };
+        for i in group {
+            for j in doubled.into_iter().flatten() {
+                join(*i, *j);
+            }
+        }
+    }
+
+    let mut clusters = Vec::<Vec<&Digest>>::new();
+    let mut cluster_of = vec![None; digests.len()];
+    for (i, digest) in digests.iter().enumerate() {
+        let root = find(&mut parents, i);
+        let cluster = *cluster_of[root].get_or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[cluster].push(digest);
+    }
+    clusters.retain(|c| c.len() > 1);
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.len()));
+
+    for (i, cluster) in clusters.iter().enumerate() {
+        println!("CLUSTER: {} ({} blobs)", i + 1, cluster.len());
+        for digest in cluster {
+            println!("  {} offset {} depth {} {}", digest.file_path, digest.offset, digest.depth, digest.digest);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+
+use crate::carve::flatten;
+use crate::config::HexBytes;
+use crate::types::{Blob, BlobSource};
+
+/// A decoding applied to a range of a blob as it is read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// XOR with a key repeated from the start of the range
+    Xor(Vec<u8>),
+    /// Standard or URL-safe base64, optionally padded, without whitespace
+    Base64,
+    /// Pairs of hex digits, without separators
+    Hex,
+    /// Reverses the bytes of each word of the given width; a trailing partial word is unchanged
+    ByteSwap(usize),
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    /// Parses `xor:KEY` (KEY in hex), `base64`, `hex` or `byteswap:WIDTH`.
+    fn from_str(s: &str) -> Result<Transform> {
+        let (name, parameter) = s.split_once(':').unwrap_or((s, ""));
+        match name {
+            "xor" => {
+                let key = parameter.parse::<HexBytes>()?.0;
+                if key.is_empty() {
+                    return Err(anyhow!("xor transform needs a key"));
+                }
+                Ok(Transform::Xor(key))
+            },
+            "base64" => Ok(Transform::Base64),
+            "hex" => Ok(Transform::Hex),
+            "byteswap" => match parameter.parse() {
+                Ok(width @ (2 | 4 | 8)) => Ok(Transform::ByteSwap(width)),
+                _ => Err(anyhow!("byteswap width must be 2, 4 or 8")),
+            },
+            _ => Err(anyhow!("unknown transform {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Xor(key) => write!(f, "xor:{}", HexBytes(key.clone())),
+            Transform::Base64 => write!(f, "base64"),
+            Transform::Hex => write!(f, "hex"),
+            Transform::ByteSwap(width) => write!(f, "byteswap:{}", width),
+        }
+    }
+}
+
+fn base64_value(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+fn hex_value(c: u8) -> u8 {
+    (c as char).to_digit(16).unwrap_or_default() as u8
+}
+
+/// A range of a blob read through a transform. Each read decodes only the encoded bytes it needs.
+pub struct Transformed {
+    data: Blob,
+    offset: usize,
+    /// Length of the encoded range
+    encoded_len: usize,
+    /// Length after decoding
+    len: usize,
+    transform: Transform,
+}
+
+impl Transformed {
+    /// Wraps a range, checking up front that it is valid for the transform so reads can't fail.
+    pub fn new(data: Blob, offset: usize, length: usize, transform: Transform) -> Result<Transformed> {
+        let end = offset.checked_add(length).ok_or_else(|| anyhow!("transform range out of bounds"))?;
+        let len = match &transform {
+            Transform::Xor(_) | Transform::ByteSwap(_) => {
+                if end > data.len() {
+                    return Err(anyhow!("transform range out of bounds"));
+                }
+                length
+            },
+            Transform::Hex => {
+                let encoded = data.range(offset, end).ok_or_else(|| anyhow!("transform range out of bounds"))?;
+                if !length.is_multiple_of(2) || !encoded.iter().all(u8::is_ascii_hexdigit) {
+                    return Err(anyhow!("range is not hex"));
+                }
+                length / 2
+            },
+            Transform::Base64 => {
+                let encoded = data.range(offset, end).ok_or_else(|| anyhow!("transform range out of bounds"))?;
+                let unpadded = encoded.iter().rposition(|c| *c != b'=').map(|p| p + 1).unwrap_or(0);
+                let padding = length - unpadded;
+                let valid = padding <= 2
+                    && (padding == 0 || length.is_multiple_of(4))
+                    && unpadded % 4 != 1
+                    && encoded[..unpadded].iter().all(|c| base64_value(*c).is_some());
+                if !valid {
+                    return Err(anyhow!("range is not base64"));
+                }
+                unpadded / 4 * 3 + (unpadded % 4).saturating_sub(1)
+            },
+        };
+        let (data, offset) = flatten(data, offset);
+        Ok(Transformed { data, offset, encoded_len: length, len, transform })
+    }
+
+    /// The range of the encoded data needed to decode `start..end`, relative to the encoded range.
+    fn encoded_range(&self, start: usize, end: usize) -> (usize, usize) {
+        let (start, end) = match &self.transform {
+            Transform::Xor(_) => (start, end),
+            Transform::Hex => (start * 2, end * 2),
+            Transform::Base64 => (start / 3 * 4, end.div_ceil(3) * 4),
+            Transform::ByteSwap(width) => (start / width * width, end.div_ceil(*width) * width),
+        };
+        (start, end.min(self.encoded_len))
+    }
+}
+
+impl BlobSource for Transformed {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.len.saturating_sub(offset));
+        if count == 0 {
+            return 0;
+        }
+        let (start, end) = self.encoded_range(offset, offset + count);
+        let Some(encoded) = self.data.range(self.offset + start, self.offset + end) else {
+            return 0;
+        };
+        // Index into the encoded range, which starts `start` bytes in
+        let at = |i: usize| encoded.get(i - start).copied();
+        for (i, out) in buf[..count].iter_mut().enumerate() {
+            let position = offset + i;
+            *out = match &self.transform {
+                Transform::Xor(key) => at(position).unwrap_or_default() ^ key[position % key.len()],
+                Transform::Hex => {
+                    let digit = |j: usize| at(position * 2 + j).map(hex_value).unwrap_or_default();
+                    digit(0) << 4 | digit(1)
+                },
+                Transform::Base64 => {
+                    let group = position / 3 * 4;
+                    let value = |j: usize| at(group + j).and_then(base64_value).unwrap_or_default();
+                    match position % 3 {
+                        0 => value(0) << 2 | value(1) >> 4,
+                        1 => value(1) << 4 | value(2) >> 2,
+                        _ => value(2) << 6 | value(3),
+                    }
+                },
+                Transform::ByteSwap(width) => {
+                    let word = position / width * width;
+                    if word + width > self.len {
+                        at(position).unwrap_or_default()
+                    } else {
+                        at(word + width - 1 - (position - word)).unwrap_or_default()
+                    }
+                },
+            };
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+
+    fn transformed(encoded: &[u8], transform: &str) -> Result<Transformed> {
+        let data: Blob = Arc::new(encoded.to_vec());
+        Transformed::new(data, 0, encoded.len(), transform.parse()?)
+    }
+
+    /// Checks every read of `blob` against `expected`, including reads that run past the end.
+    fn assert_reads(blob: &Transformed, expected: &[u8]) {
+        assert_eq!(blob.len(), expected.len());
+        for offset in 0..=expected.len() + 1 {
+            for length in 0..=expected.len() + 1 {
+                let mut buf = vec![0u8; length];
+                let count = blob.read_at(offset, &mut buf);
+                let want = expected.get(offset..).unwrap_or_default();
+                let want = &want[..want.len().min(length)];
+                assert_eq!(&buf[..count], want, "read of {} bytes at {}", length, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn base64_groups() {
+        assert_reads(&transformed(b"aGVsbG8gd29ybGQh", "base64").unwrap(), b"hello world!");
+        assert_reads(&transformed(b"aGVsbG8=", "base64").unwrap(), b"hello");
+        assert_reads(&transformed(b"aGVsbG8", "base64").unwrap(), b"hello");
+        assert_reads(&transformed(b"aGVsbA==", "base64").unwrap(), b"hell");
+        assert_reads(&transformed(b"aGVsbA", "base64").unwrap(), b"hell");
+        assert_reads(&transformed(b"-_8", "base64").unwrap(), &[0xfb, 0xff]);
+    }
+
+    #[test]
+    fn base64_rejects_invalid() {
+        assert!(transformed(b"aGVsb", "base64").is_err());
+        assert!(transformed(b"aGVsbA===", "base64").is_err());
+        assert!(transformed(b"aGVsbA=", "base64").is_err());
+        assert!(transformed(b"aGV$bA==", "base64").is_err());
+    }
+
+    #[test]
+    fn byteswap_leaves_partial_word() {
+        assert_reads(&transformed(b"ABCDEFG", "byteswap:2").unwrap(), b"BADCFEG");
+        assert_reads(&transformed(b"ABCDEFG", "byteswap:4").unwrap(), b"DCBAEFG");
+        assert_reads(&transformed(b"ABCDEFGHIJ", "byteswap:8").unwrap(), b"HGFEDCBAIJ");
+        assert_reads(&transformed(b"ABC", "byteswap:8").unwrap(), b"ABC");
+    }
+
+    #[test]
+    fn byteswap_width() {
+        assert!("byteswap:3".parse::<Transform>().is_err());
+        assert!("byteswap:0".parse::<Transform>().is_err());
+        assert!("byteswap:16".parse::<Transform>().is_err());
+    }
+
+    #[test]
+    fn xor_and_hex() {
+        assert_reads(&transformed(&[0x32, 0x3f, 0x36, 0x36, 0x35], "xor:5a").unwrap(), b"hello");
+        assert_reads(&transformed(&[0x69, 0x67, 0x6d, 0x6e, 0x6e], "xor:0102").unwrap(), b"hello");
+        assert_reads(&transformed(b"68656C6c6f", "hex").unwrap(), b"hello");
+        assert!(transformed(b"68656", "hex").is_err());
+        assert!(transformed(b"6x", "hex").is_err());
+    }
+
+    #[test]
+    fn encoded_range_covers_whole_groups() {
+        let base64 = transformed(b"aGVsbG8=", "base64").unwrap();
+        assert_eq!(base64.encoded_range(0, 1), (0, 4));
+        assert_eq!(base64.encoded_range(2, 4), (0, 8));
+        assert_eq!(base64.encoded_range(4, 5), (4, 8));
+        let byteswap = transformed(b"ABCDEFG", "byteswap:4").unwrap();
+        assert_eq!(byteswap.encoded_range(1, 2), (0, 4));
+        assert_eq!(byteswap.encoded_range(5, 7), (4, 7));
+        let hex = transformed(b"68656c6c6f", "hex").unwrap();
+        assert_eq!(hex.encoded_range(1, 3), (2, 6));
+    }
+
+    #[test]
+    fn reads_through_carve() {
+        let data: Blob = Arc::new(b"xxaGVsbG8=yy".to_vec());
+        let carve: Blob = Arc::new(crate::carve::Carve::new(data, 2, 8).unwrap());
+        assert_reads(&Transformed::new(carve, 0, 8, Transform::Base64).unwrap(), b"hello");
+    }
+}
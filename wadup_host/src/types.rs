@@ -1,12 +1,116 @@
+use std::borrow::Cow;
 use std::sync::Arc;
 
-pub type Blob = Arc<dyn AsRef<[u8]> + Sync + Send>;
+/// Random access to the bytes of a blob, which need not be held in one piece in memory.
+pub trait BlobSource: Send + Sync {
+    fn len(&self) -> usize;
 
-#[allow(dead_code)]
-#[derive(Debug)]
+    /// Copies bytes starting at `offset` into `buf` and returns how many were copied, fewer than
+    /// `buf.len()` only at the end of the blob.
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+
+    /// The whole blob as one slice, for sources that hold it contiguously.
+    fn as_slice(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// For a window onto another source, that source and the window's offset in it.
+    fn window(&self) -> Option<(&Blob, usize)> {
+        None
+    }
+
+    /// Bytes `start..end`, borrowed when the source is contiguous and copied otherwise, or `None`
+    /// if the range is out of bounds.
+    fn range(&self, start: usize, end: usize) -> Option<Cow<'_, [u8]>> {
+        if start > end || end > self.len() {
+            return None;
+        }
+        if let Some(data) = self.as_slice() {
+            return Some(Cow::Borrowed(&data[start..end]));
+        }
+        let mut data = vec![0u8; end - start];
+        self.read_at(start, &mut data);
+        Some(Cow::Owned(data))
+    }
+
+    /// The whole blob, for consumers that need one slice.
+    fn bytes(&self) -> Cow<'_, [u8]> {
+        self.range(0, self.len()).unwrap_or_default()
+    }
+}
+
+impl BlobSource for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let available = self.get(offset..).unwrap_or_default();
+        let count = buf.len().min(available.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        count
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+}
+
+impl BlobSource for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        BlobSource::read_at(&self[..], offset, buf)
+    }
+
+    fn as_slice(&self) -> Option<&[u8]> {
+        Some(self)
+    }
+}
+
+pub type Blob = Arc<dyn BlobSource>;
+
+#[allow(dead_code, clippy::enum_variant_names)]
+#[derive(Clone, Debug)]
 pub enum DataValue {
     StringValue(String),
     Int64Value(i64),
     Float64Value(f64),
     NoneValue,
 }
+
+impl DataValue {
+    /// The value as passed to modules: a type letter followed by the value as text.
+    pub fn encode(&self) -> String {
+        match self {
+            DataValue::StringValue(value) => format!("s{}", value),
+            DataValue::Int64Value(value) => format!("i{}", value),
+            DataValue::Float64Value(value) => format!("f{}", value),
+            DataValue::NoneValue => "n".to_owned(),
+        }
+    }
+
+    /// The value as stored in the results file. Non-finite floats have no JSON form and are null.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            DataValue::StringValue(value) => value.clone().into(),
+            DataValue::Int64Value(value) => (*value).into(),
+            DataValue::Float64Value(value) => serde_json::Number::from_f64(*value).map(Into::into).unwrap_or_default(),
+            DataValue::NoneValue => serde_json::Value::Null,
+        }
+    }
+
+    pub fn from_json(value: &serde_json::Value) -> DataValue {
+        match value {
+            serde_json::Value::String(value) => DataValue::StringValue(value.clone()),
+            serde_json::Value::Number(value) => match value.as_i64() {
+                Some(value) => DataValue::Int64Value(value),
+                None => DataValue::Float64Value(value.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::Bool(value) => DataValue::Int64Value(*value as i64),
+            _ => DataValue::NoneValue,
+        }
+    }
+}
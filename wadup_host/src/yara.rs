@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use yara_x::{Compiler, MetaValue, Scanner};
+
+use crate::provenance::Provenance;
+use crate::rows::Row;
+use crate::types::DataValue;
+
+/// Schema of the rows emitted for every rule that matches a blob.
+pub const YARA_SCHEMA: &str = "wadup_yara";
+
+/// YARA rules compiled with yara-x.
+pub struct Rules {
+    rules: yara_x::Rules,
+}
+
+#[derive(Clone, Debug)]
+pub struct StringMatch {
+    pub identifier: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleMatch {
+    pub namespace: String,
+    pub rule: String,
+    pub tags: Vec<String>,
+    pub strings: Vec<StringMatch>,
+    /// Set by `wadup_carve = true` in the rule's meta: carve at each match offset, to the end of
+    /// the blob or for `wadup_carve_length` bytes
+    pub carve: Option<Option<u64>>,
+}
+
+/// Compiles every `.yar` and `.yara` file in a directory; each file is its own namespace, named
+/// after the file. A file that can't be read or compiled is skipped rather than stopping the run,
+/// and returned among the warnings.
+pub fn load_rules(directory: &Path) -> Result<(Rules, Vec<String>)> {
+    let mut paths = fs::read_dir(directory)
+        .map_err(|e| anyhow!("unable to read YARA rules directory {:?}: {}", directory, e))?
+        .filter_map(|p| p.ok())
+        .map(|p| p.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "yar" || e == "yara"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut compiler = Compiler::new();
+    let mut warnings = Vec::new();
+    for path in paths {
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                warnings.push(format!("skipping YARA rules {:?}: {}", path, e));
+                continue;
+            },
+        };
+        let namespace = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        // Only the rules of files that compile are kept
+        if let Err(e) = compiler.new_namespace(&namespace).add_source(source.as_str()) {
+            warnings.push(format!("skipping YARA rules {:?}: {}", path, e));
+        }
+    }
+    Ok((Rules { rules: compiler.build() }, warnings))
+}
+
+impl Rules {
+    pub fn scan(&self, data: &[u8]) -> Result<Vec<RuleMatch>> {
+        let mut scanner = Scanner::new(&self.rules);
+        let results = scanner.scan(data).map_err(|e| anyhow!("YARA scan failed: {}", e))?;
+        Ok(results.matching_rules()
+            .map(|rule| {
+                let strings = rule.patterns()
+                    .flat_map(|pattern| pattern.matches().map(move |m| StringMatch {
+                        identifier: pattern.identifier().to_owned(),
+                        offset: m.range().start as u64,
+                        length: m.range().len() as u64,
+                    }))
+                    .collect();
+                let meta = |key: &str| rule.metadata().find(|(k, _)| *k == key).map(|(_, v)| v);
+                let carve = (meta("wadup_carve") == Some(MetaValue::Bool(true))).then(|| match meta("wadup_carve_length") {
+                    Some(MetaValue::Integer(length)) => u64::try_from(length).ok(),
+                    _ => None,
+                });
+                RuleMatch {
+                    namespace: rule.namespace().to_owned(),
+                    rule: rule.identifier().to_owned(),
+                    tags: rule.tags().map(|t| t.identifier().to_owned()).collect(),
+                    strings,
+                    carve,
+                }
+            })
+            .collect())
+    }
+}
+
+/// The row for a rule match: where the blob came from, the rule and where its strings matched.
+pub fn yara_row(rule_match: &RuleMatch, provenance: &Provenance) -> Row {
+    let strings = rule_match.strings.iter()
+        .map(|s| format!("{}@{}", s.identifier, s.offset))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Row {
+        schema: YARA_SCHEMA.to_owned(),
+        values: vec![
+            ("file_path".to_owned(), DataValue::StringValue(provenance.file_path.to_string_lossy().into_owned())),
+            ("offset".to_owned(), DataValue::Int64Value(provenance.offset as i64)),
+            ("depth".to_owned(), DataValue::Int64Value(provenance.depth as i64)),
+            ("namespace".to_owned(), DataValue::StringValue(rule_match.namespace.clone())),
+            ("rule".to_owned(), DataValue::StringValue(rule_match.rule.clone())),
+            ("tags".to_owned(), DataValue::StringValue(rule_match.tags.join(" "))),
+            ("strings".to_owned(), DataValue::StringValue(strings)),
+        ],
+        tags: provenance.tags.snapshot(),
+    }
+}
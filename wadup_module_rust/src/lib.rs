@@ -1,7 +1,7 @@
 use std::io::Read;
 use serde::Serialize;
 use anyhow::Error;
-use wadup_bindings::{WadupInput, WadupOutput, wadup_start};
+use wadup_bindings::{WadupInput, WadupOutput, wadup_signatures, wadup_start};
 
 #[derive(Serialize)]
 struct TestData {
@@ -16,6 +16,7 @@ pub fn read_u32_le<R: Read>(input: &mut R) -> Result<u32, Error> {
 }
 
 wadup_start!(main);
+wadup_signatures!("0:4444");
 
 fn main() -> Result<(), Error> {
     let mut input = WadupInput::new();